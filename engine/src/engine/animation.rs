@@ -0,0 +1,394 @@
+use std::fmt;
+
+use super::cell::CellCoord;
+use super::layout::Point;
+use super::renderer::{Sprite, Viewport};
+
+/// A value that can be interpolated between two keyframes.
+pub trait Tween: Clone {
+    /// Returns the value `t` of the way from `self` to `to`, where `t` is between 0.0 and 1.0.
+    fn tween(&self, to: &Self, t: f32) -> Self;
+}
+
+impl Tween for f32 {
+    fn tween(&self, to: &f32, t: f32) -> f32 {
+        self + (to - self) * t
+    }
+}
+
+impl Tween for Point {
+    fn tween(&self, to: &Point, t: f32) -> Point {
+        Point::new(self.x.tween(&to.x, t), self.y.tween(&to.y, t))
+    }
+}
+
+/// An RGB color that can be animated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub fn new(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b }
+    }
+
+    /// Parses a color written as `#rrggbb`.
+    pub fn from_hex(hex: &str) -> Option<Color> {
+        let digits = hex.strip_prefix('#')?;
+        if digits.len() != 6 {
+            return None;
+        }
+
+        let channel = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok();
+
+        Some(Color::new(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl Tween for Color {
+    fn tween(&self, to: &Color, t: f32) -> Color {
+        let channel = |from: u8, to: u8| (from as f32).tween(&(to as f32), t).round() as u8;
+
+        Color::new(
+            channel(self.r, to.r),
+            channel(self.g, to.g),
+            channel(self.b, to.b),
+        )
+    }
+}
+
+/// How an animation speeds up and slows down over its duration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps the linear progress `t` of an animation to its eased progress.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    -1.0 + (4.0 - 2.0 * t) * t
+                }
+            }
+        }
+    }
+}
+
+/// A value reached `at` a fraction of the way through an animation.
+#[derive(Debug, Clone)]
+pub struct Keyframe<T> {
+    pub at: f32,
+    pub value: T,
+}
+
+impl<T> Keyframe<T> {
+    pub fn new(at: f32, value: T) -> Keyframe<T> {
+        Keyframe { at, value }
+    }
+}
+
+// Returns the value of `keyframes` at `t`. Keyframes must be ordered by `at`.
+fn sample<T: Tween>(keyframes: &[Keyframe<T>], t: f32) -> Option<T> {
+    let first = keyframes.first()?;
+    if t <= first.at {
+        return Some(first.value.clone());
+    }
+
+    for pair in keyframes.windows(2) {
+        let (from, to) = (&pair[0], &pair[1]);
+        if t <= to.at {
+            let local = (t - from.at) / (to.at - from.at);
+            return Some(from.value.tween(&to.value, local));
+        }
+    }
+
+    keyframes.last().map(|last| last.value.clone())
+}
+
+/// Keyframed changes to a sprite over time. Each property only changes if it has keyframes.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    /// Length of the animation in milliseconds.
+    pub duration: f64,
+    pub easing: Easing,
+    /// Indicates if the animation starts over once it ends.
+    pub repeat: bool,
+    pub position: Vec<Keyframe<Point>>,
+    pub rotation: Vec<Keyframe<f32>>,
+    pub scale: Vec<Keyframe<f32>>,
+    pub opacity: Vec<Keyframe<f32>>,
+    /// Background color of the sprite's texture.
+    pub color: Vec<Keyframe<Color>>,
+}
+
+impl Animation {
+    pub fn new(duration: f64) -> Animation {
+        Animation {
+            duration,
+            easing: Easing::Linear,
+            repeat: false,
+            position: Vec::new(),
+            rotation: Vec::new(),
+            scale: Vec::new(),
+            opacity: Vec::new(),
+            color: Vec::new(),
+        }
+    }
+
+    pub fn is_finished(&self, elapsed: f64) -> bool {
+        !self.repeat && elapsed >= self.duration
+    }
+
+    /// Returns the eased progress, from 0.0 to 1.0, after `elapsed` milliseconds.
+    pub fn progress(&self, elapsed: f64) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+
+        let linear = if self.repeat {
+            (elapsed % self.duration) / self.duration
+        } else {
+            (elapsed / self.duration).min(1.0)
+        };
+
+        self.easing.apply(linear.max(0.0) as f32)
+    }
+
+    /// Sets the properties of `sprite` to their values after `elapsed` milliseconds.
+    pub fn apply(&self, sprite: &mut Sprite, elapsed: f64) {
+        let t = self.progress(elapsed);
+
+        if let Some(position) = sample(&self.position, t) {
+            sprite.set_position(&position);
+        }
+        if let Some(rotation) = sample(&self.rotation, t) {
+            sprite.set_rotation(rotation);
+        }
+        if let Some(scale) = sample(&self.scale, t) {
+            sprite.set_scale(scale);
+        }
+        if let Some(opacity) = sample(&self.opacity, t) {
+            sprite.set_opacity(opacity);
+        }
+        if let Some(color) = sample(&self.color, t) {
+            let mut texture = sprite.texture().clone();
+            texture.background_color = Some(color.to_string());
+            sprite.set_texture(&texture);
+        }
+    }
+}
+
+// An animation playing on the sprite at `coord` of the layer `layer_name`.
+struct Playing {
+    layer_name: String,
+    coord: CellCoord,
+    animation: Animation,
+    // Time of the first frame the animation was updated on.
+    start: Option<f64>,
+}
+
+/// Plays animations on the sprites of a viewport.
+#[derive(Default)]
+pub struct Animator {
+    playing: Vec<Playing>,
+}
+
+impl Animator {
+    pub fn new() -> Animator {
+        Animator::default()
+    }
+
+    /// Plays `animation` on the sprite at `coord` of the layer `layer_name`, starting from the
+    /// next update. Any animation already playing on the sprite is stopped.
+    pub fn play(&mut self, layer_name: &str, coord: CellCoord, animation: Animation) {
+        self.stop(layer_name, &coord);
+        self.playing.push(Playing {
+            layer_name: layer_name.to_owned(),
+            coord,
+            animation,
+            start: None,
+        });
+    }
+
+    /// Stops the animation on a sprite, leaving the sprite as it is.
+    pub fn stop(&mut self, layer_name: &str, coord: &CellCoord) {
+        self.playing
+            .retain(|playing| playing.layer_name != layer_name || playing.coord != *coord);
+    }
+
+    pub fn is_playing(&self, layer_name: &str, coord: &CellCoord) -> bool {
+        self.playing
+            .iter()
+            .any(|playing| playing.layer_name == layer_name && playing.coord == *coord)
+    }
+
+    pub fn len(&self) -> usize {
+        self.playing.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.playing.is_empty()
+    }
+
+    /// Updates every animation to the time `now` in milliseconds, such as the time given to a
+    /// `requestAnimationFrame` callback. Finished animations, and animations whose sprite was
+    /// removed, are dropped.
+    pub fn update(&mut self, now: f64, viewport: &mut Viewport) {
+        self.playing.retain_mut(|playing| {
+            let start = *playing.start.get_or_insert(now);
+            let elapsed = now - start;
+
+            let sprite = match viewport.layer_mut(&playing.layer_name) {
+                Some(layer) => layer.sprite_mut(&playing.coord),
+                None => None,
+            };
+
+            match sprite {
+                Some(sprite) => {
+                    playing.animation.apply(sprite, elapsed);
+                    !playing.animation.is_finished(elapsed)
+                }
+                None => false,
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::headless::HeadlessBackend;
+    use super::super::renderer::{Layer, Shape, Texture};
+    use super::*;
+
+    fn sprite() -> Sprite {
+        Sprite::new("a", &Shape::Cell, &Point::origin(), &Texture::new())
+    }
+
+    #[test]
+    fn easing_01() {
+        for easing in &[
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+        }
+
+        assert_eq!(Easing::EaseIn.apply(0.5), 0.25);
+        assert_eq!(Easing::EaseOut.apply(0.5), 0.75);
+        assert_eq!(Easing::EaseInOut.apply(0.25), 0.125);
+    }
+
+    #[test]
+    fn color_01() {
+        let from = Color::from_hex("#000080").unwrap();
+        let to = Color::from_hex("#ff0000").unwrap();
+
+        assert_eq!(from.tween(&to, 0.5).to_string(), "#800040");
+        assert_eq!(Color::from_hex("red"), None);
+        assert_eq!(Color::from_hex("#12345"), None);
+    }
+
+    #[test]
+    fn apply_01() {
+        let mut animation = Animation::new(1000.0);
+        animation.position = vec![
+            Keyframe::new(0.0, Point::new(0.0, 0.0)),
+            Keyframe::new(1.0, Point::new(100.0, -50.0)),
+        ];
+        animation.scale = vec![
+            Keyframe::new(0.0, 1.0),
+            Keyframe::new(0.5, 2.0),
+            Keyframe::new(1.0, 1.0),
+        ];
+        animation.color = vec![
+            Keyframe::new(0.0, Color::new(0, 0, 0)),
+            Keyframe::new(1.0, Color::new(200, 100, 0)),
+        ];
+
+        let mut sprite = sprite();
+        animation.apply(&mut sprite, 250.0);
+        assert_eq!(*sprite.position(), Point::new(25.0, -12.5));
+        assert_eq!(sprite.scale(), 1.5);
+        assert_eq!(
+            sprite.texture().background_color,
+            Some("#321900".to_owned())
+        );
+        // Properties without keyframes are left alone.
+        assert_eq!(sprite.rotation(), 0.0);
+        assert_eq!(sprite.opacity(), 1.0);
+
+        // Past the end the last keyframe holds.
+        animation.apply(&mut sprite, 5000.0);
+        assert_eq!(*sprite.position(), Point::new(100.0, -50.0));
+        assert_eq!(sprite.scale(), 1.0);
+        assert!(animation.is_finished(5000.0));
+    }
+
+    #[test]
+    fn repeat_01() {
+        let mut animation = Animation::new(100.0);
+        animation.repeat = true;
+        animation.opacity = vec![Keyframe::new(0.0, 1.0), Keyframe::new(1.0, 0.0)];
+
+        let mut sprite = sprite();
+        animation.apply(&mut sprite, 250.0);
+
+        assert_eq!(sprite.opacity(), 0.5);
+        assert!(!animation.is_finished(250.0));
+    }
+
+    #[test]
+    fn animator_01() {
+        let mut viewport = Viewport::with_backend(Box::new(HeadlessBackend::new(100, 100)));
+        let mut layer = Layer::new("items");
+        let coord = CellCoord::new(0, 0, 0);
+        layer.add_sprite(coord, sprite());
+        viewport.insert_layer(0, layer);
+
+        let mut animation = Animation::new(100.0);
+        animation.rotation = vec![Keyframe::new(0.0, 0.0), Keyframe::new(1.0, 60.0)];
+
+        let mut animator = Animator::new();
+        animator.play("items", coord, animation);
+
+        // The animation starts on the first update.
+        animator.update(1000.0, &mut viewport);
+        animator.update(1050.0, &mut viewport);
+        let rotation = |viewport: &Viewport| {
+            viewport
+                .layer("items")
+                .unwrap()
+                .sprite(&coord)
+                .unwrap()
+                .rotation()
+        };
+        assert_eq!(rotation(&viewport), 30.0);
+        assert!(animator.is_playing("items", &coord));
+
+        animator.update(1100.0, &mut viewport);
+        assert_eq!(rotation(&viewport), 60.0);
+        assert!(animator.is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+// A texture atlas holds the textures shared by many sprites. In SVG each texture is written once
+// into the `<defs>` of the document and every sprite using it only holds a reference by id.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::renderer::RenderError;
+use super::svg::SvgNode;
+
+/// A texture defined once and shared by every sprite that names it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TextureDef {
+    /// An image drawn over the sprite, centered on its position.
+    Symbol {
+        image: String,
+        width: i32,
+        height: i32,
+    },
+    /// An image repeated to fill the sprite's shape.
+    Pattern {
+        image: String,
+        width: i32,
+        height: i32,
+    },
+    /// Colors blended across the sprite's shape from left to right. Each stop is an offset from
+    /// 0.0 to 1.0 and the color at that offset.
+    Gradient { stops: Vec<(f32, String)> },
+}
+
+/// The named textures that sprites can share.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextureAtlas {
+    // Ordered by name so definitions are always written in the same order.
+    textures: BTreeMap<String, TextureDef>,
+}
+
+impl TextureAtlas {
+    pub fn new() -> TextureAtlas {
+        TextureAtlas::default()
+    }
+
+    /// Loads an atlas from a RON manifest such as
+    /// `(textures: {"factory": Symbol(image: "factory.svg", width: 15, height: 15)})`.
+    pub fn from_ron(manifest: &str) -> Result<TextureAtlas, RenderError> {
+        ron::de::from_str(manifest).map_err(|error| {
+            RenderError::new(format!("invalid texture atlas manifest: {}", error).as_str())
+        })
+    }
+
+    /// Adds the texture `name`, replacing any texture with the same name.
+    pub fn insert(&mut self, name: &str, texture: TextureDef) {
+        self.textures.insert(name.to_owned(), texture);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TextureDef> {
+        self.textures.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    /// Returns the id of the SVG definition of the texture `name`.
+    pub fn def_id(name: &str) -> String {
+        format!("texture_{}", name)
+    }
+
+    /// Returns the `<defs>` element defining every texture.
+    pub fn defs(&self) -> SvgNode {
+        let mut defs = SvgNode::new("defs");
+
+        for (name, texture) in &self.textures {
+            let mut def = match texture {
+                TextureDef::Symbol {
+                    image,
+                    width,
+                    height,
+                } => {
+                    // Let the image spill out of the symbol so it can be centered on the origin.
+                    let mut symbol = SvgNode::new("symbol");
+                    symbol.set_attribute("overflow", "visible");
+                    symbol.append_child(image_node(image, *width, *height, true));
+                    symbol
+                }
+                TextureDef::Pattern {
+                    image,
+                    width,
+                    height,
+                } => {
+                    let mut pattern = SvgNode::new("pattern");
+                    pattern.set_attribute("patternUnits", "userSpaceOnUse");
+                    pattern.set_attribute("width", width.to_string().as_str());
+                    pattern.set_attribute("height", height.to_string().as_str());
+                    pattern.append_child(image_node(image, *width, *height, false));
+                    pattern
+                }
+                TextureDef::Gradient { stops } => {
+                    let mut gradient = SvgNode::new("linearGradient");
+                    for (offset, color) in stops {
+                        let mut stop = SvgNode::new("stop");
+                        stop.set_attribute("offset", offset.to_string().as_str());
+                        stop.set_attribute("stop-color", color);
+                        gradient.append_child(stop);
+                    }
+                    gradient
+                }
+            };
+
+            // The id goes first to make the markup easier to read.
+            def.attributes
+                .insert(0, ("id".to_owned(), TextureAtlas::def_id(name)));
+            defs.append_child(def);
+        }
+
+        defs
+    }
+}
+
+fn image_node(href: &str, width: i32, height: i32, centered: bool) -> SvgNode {
+    let mut image = SvgNode::new("image");
+    image.set_attribute("href", href);
+    image.set_attribute("width", width.to_string().as_str());
+    image.set_attribute("height", height.to_string().as_str());
+    if centered {
+        image.set_attribute("x", (-width as f32 / 2.0).to_string().as_str());
+        image.set_attribute("y", (-height as f32 / 2.0).to_string().as_str());
+    }
+
+    image
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_ron_01() {
+        let atlas = TextureAtlas::from_ron(
+            r#"(
+                textures: {
+                    "factory": Symbol(image: "factory.svg", width: 15, height: 10),
+                    "belt": Gradient(stops: [(0.0, "gray"), (1.0, "dimgray")]),
+                },
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(atlas.len(), 2);
+        assert_eq!(
+            atlas.get("factory"),
+            Some(&TextureDef::Symbol {
+                image: "factory.svg".to_owned(),
+                width: 15,
+                height: 10,
+            })
+        );
+        assert!(TextureAtlas::from_ron("(textures: 1)").is_err());
+    }
+
+    #[test]
+    fn defs_01() {
+        let mut atlas = TextureAtlas::new();
+        atlas.insert(
+            "factory",
+            TextureDef::Symbol {
+                image: "factory.svg".to_owned(),
+                width: 15,
+                height: 10,
+            },
+        );
+        atlas.insert(
+            "belt",
+            TextureDef::Gradient {
+                stops: vec![(0.0, "gray".to_owned()), (1.0, "dimgray".to_owned())],
+            },
+        );
+
+        assert_eq!(
+            atlas.defs().to_string(),
+            "<defs>\
+             <linearGradient id=\"texture_belt\">\
+             <stop offset=\"0\" stop-color=\"gray\"/><stop offset=\"1\" stop-color=\"dimgray\"/>\
+             </linearGradient>\
+             <symbol id=\"texture_factory\" overflow=\"visible\">\
+             <image href=\"factory.svg\" width=\"15\" height=\"10\" x=\"-7.5\" y=\"-5\"/>\
+             </symbol></defs>"
+        );
+    }
+}
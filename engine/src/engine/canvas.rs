@@ -0,0 +1,373 @@
+use std::cell;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use super::atlas::{TextureAtlas, TextureDef};
+use super::layout::Point;
+use super::renderer::{
+    create_html_element, get_target, Backend, Camera, CellGeometry, Layer, RenderError, Shape,
+    Sprite, TextAnchor,
+};
+
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
+
+use js_sys::Array;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Renders sprites by drawing onto a canvas inside a target element. Unlike SVG, nothing drawn
+/// is kept by the browser, so every change redraws all layers.
+pub struct CanvasBackend {
+    canvas: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    camera: Camera,
+    images: HashMap<String, HtmlImageElement>,
+    atlas: TextureAtlas,
+    // Names of the layers drawn by the last render, in order.
+    drawn_layers: Vec<String>,
+    // Indicates that the canvas no longer matches what the camera sees. Shared with the images
+    // being loaded, which were left out of the canvas until they finish.
+    stale: Rc<cell::Cell<bool>>,
+}
+
+impl CanvasBackend {
+    pub fn new(target_id: &str, width: i32, height: i32) -> Result<CanvasBackend, RenderError> {
+        // Setup the world render target. This must be done only once.
+        let target = get_target(target_id)?;
+        let canvas = create_html_element("canvas")?.dyn_into::<HtmlCanvasElement>()?;
+        canvas.set_id(&format!("{}_canvas", target_id));
+        canvas.set_width(width as u32);
+        canvas.set_height(height as u32);
+        target.append_child(&canvas)?;
+
+        let context = canvas
+            .get_context("2d")?
+            .ok_or_else(|| RenderError::new("canvas does not support a 2d context"))?
+            .dyn_into::<CanvasRenderingContext2d>()
+            .map_err(|_| RenderError::new("canvas context is not a 2d context"))?;
+
+        Ok(CanvasBackend {
+            canvas,
+            context,
+            camera: Camera::new(width, height),
+            images: HashMap::new(),
+            atlas: TextureAtlas::new(),
+            drawn_layers: Vec::new(),
+            stale: Rc::new(cell::Cell::new(true)),
+        })
+    }
+
+    /// Returns the loaded image for `href`. Images load in the background, so `None` is
+    /// returned until the browser has finished loading it.
+    fn image(&mut self, href: &str) -> Result<Option<HtmlImageElement>, RenderError> {
+        if !self.images.contains_key(href) {
+            let image = create_html_element("img")?.dyn_into::<HtmlImageElement>()?;
+
+            // Draw everything again on the next render once the image can be drawn.
+            let stale = Rc::clone(&self.stale);
+            let onload = Closure::wrap(Box::new(move || stale.set(true)) as Box<dyn FnMut()>);
+            image.set_onload(Some(onload.as_ref().unchecked_ref()));
+            // The image is kept for as long as the backend, so its handler is never removed.
+            onload.forget();
+
+            image.set_src(href);
+            self.images.insert(href.to_owned(), image);
+        }
+
+        let image = &self.images[href];
+        if image.complete() && image.natural_width() > 0 {
+            Ok(Some(image.clone()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn redraw(&mut self, layers: &[Layer], cell: &CellGeometry) -> Result<(), RenderError> {
+        let scale_x = self.canvas.width() as f64 / self.camera.view_width() as f64;
+        let scale_y = self.canvas.height() as f64 / self.camera.view_height() as f64;
+
+        self.context.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)?;
+        self.context.clear_rect(
+            0.0,
+            0.0,
+            self.canvas.width() as f64,
+            self.canvas.height() as f64,
+        );
+
+        // Map the camera's view box onto the canvas. Zooming changes the size of the view box.
+        self.context.set_transform(
+            scale_x,
+            0.0,
+            0.0,
+            scale_y,
+            -self.camera.min_x() as f64 * scale_x,
+            -self.camera.min_y() as f64 * scale_y,
+        )?;
+
+        for layer in layers {
+            if !layer.is_visible() {
+                continue;
+            }
+
+            // Sprites are drawn over each other in order.
+            for sprite in layer.shown_sprites() {
+                self.draw_sprite(sprite, layer.opacity(), cell)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw_sprite(
+        &mut self,
+        sprite: &Sprite,
+        layer_opacity: f32,
+        cell: &CellGeometry,
+    ) -> Result<(), RenderError> {
+        let context = self.context.clone();
+
+        context.save();
+        // All sprite data is defined about the origin.
+        context.translate(sprite.position().x as f64, sprite.position().y as f64)?;
+        context.rotate(sprite.rotation().to_radians() as f64)?;
+        context.scale(sprite.scale() as f64, sprite.scale() as f64)?;
+        context.set_global_alpha((sprite.opacity() * layer_opacity) as f64);
+
+        // Text is drawn rather than traced. Tooltips are only shown by the SVG backend.
+        if let Shape::Text {
+            text,
+            font,
+            size,
+            anchor,
+        } = sprite.shape()
+        {
+            context.set_font(&format!("{}px {}", size, font));
+            context.set_text_align(match anchor {
+                TextAnchor::Start => "left",
+                TextAnchor::Middle => "center",
+                TextAnchor::End => "right",
+            });
+            context.set_text_baseline("middle");
+            let color = sprite.texture().background_color.as_deref();
+            context.set_fill_style_str(color.unwrap_or("black"));
+            context.fill_text(text, 0.0, 0.0)?;
+            context.restore();
+
+            return Ok(());
+        }
+
+        let (width, height) = trace_shape(&context, sprite.shape(), cell)?;
+
+        // Lines are drawn with their own stroke instead of the texture.
+        if let Shape::Polyline { points, stroke } | Shape::Path { points, stroke } = sprite.shape()
+        {
+            let dash = Array::new();
+            for length in &stroke.dash {
+                dash.push(&JsValue::from(*length));
+            }
+
+            context.set_stroke_style_str(&stroke.color);
+            context.set_line_width(stroke.width as f64);
+            context.set_line_dash(&dash)?;
+            context.set_line_cap("round");
+            context.set_line_join("round");
+            context.stroke();
+
+            context.set_fill_style_str(&stroke.color);
+            for arrowhead in stroke.arrowheads(points) {
+                context.begin_path();
+                trace_points(&context, &arrowhead);
+                context.close_path();
+                context.fill();
+            }
+            context.restore();
+
+            return Ok(());
+        }
+
+        let texture = sprite.texture();
+        let shared = texture
+            .name
+            .as_ref()
+            .and_then(|name| self.atlas.get(name))
+            .cloned();
+
+        // A pattern or gradient from the atlas fills the shape instead of the background color.
+        match &shared {
+            Some(TextureDef::Pattern { image, .. }) => {
+                if let Some(image) = self.image(image)? {
+                    if let Some(pattern) =
+                        context.create_pattern_with_html_image_element(&image, "repeat")?
+                    {
+                        context.set_fill_style_canvas_pattern(&pattern);
+                        context.fill();
+                    }
+                }
+            }
+            Some(TextureDef::Gradient { stops }) => {
+                let gradient = context.create_linear_gradient(-width / 2.0, 0.0, width / 2.0, 0.0);
+                for (offset, color) in stops {
+                    gradient.add_color_stop(*offset, color)?;
+                }
+                context.set_fill_style_canvas_gradient(&gradient);
+                context.fill();
+            }
+            _ => {
+                if let Some(bg_color) = &texture.background_color {
+                    context.set_fill_style_str(bg_color);
+                    context.fill();
+                }
+            }
+        }
+        if let Some(border) = &texture.border {
+            context.set_stroke_style_str(border.color());
+            context.set_line_width(border.width() as f64);
+            context.stroke();
+        }
+
+        // A symbol from the atlas is drawn at its own size instead of the image.
+        let image = match &shared {
+            Some(TextureDef::Symbol {
+                image,
+                width,
+                height,
+            }) => Some((image.clone(), *width as f64, *height as f64)),
+            _ => texture.image.clone().map(|href| (href, width, height)),
+        };
+
+        if let Some((href, width, height)) = image {
+            if let Some(image) = self.image(&href)? {
+                context.draw_image_with_html_image_element_and_dw_and_dh(
+                    &image,
+                    -width / 2.0,
+                    -height / 2.0,
+                    width,
+                    height,
+                )?;
+            }
+        }
+
+        context.restore();
+
+        Ok(())
+    }
+}
+
+/// Traces the outline of a shape about the origin as the current path. Returns the width and
+/// height of the shape.
+fn trace_shape(
+    context: &CanvasRenderingContext2d,
+    shape: &Shape,
+    cell: &CellGeometry,
+) -> Result<(f64, f64), RenderError> {
+    context.begin_path();
+
+    match shape {
+        Shape::Cell => {
+            trace_points(context, &cell.corners);
+            context.close_path();
+
+            Ok((cell.size as f64, cell.size as f64))
+        }
+        Shape::Rectangle { width, height } => {
+            let (width, height) = (*width as f64, *height as f64);
+            context.rect(-width / 2.0, -height / 2.0, width, height);
+
+            Ok((width, height))
+        }
+        Shape::Circle { radius } => {
+            let radius = *radius as f64;
+            context.arc(0.0, 0.0, radius, 0.0, 2.0 * PI)?;
+
+            Ok((radius, radius))
+        }
+        Shape::Polyline { points, stroke: _ } => {
+            trace_points(context, points);
+
+            Ok((0.0, 0.0))
+        }
+        Shape::Path { points, stroke: _ } => {
+            // Round each corner from the middle of the line before it to the middle of the
+            // line after it.
+            for (i, point) in points.iter().enumerate() {
+                if i == 0 {
+                    context.move_to(point.x as f64, point.y as f64);
+                } else if i == points.len() - 1 {
+                    context.line_to(point.x as f64, point.y as f64);
+                } else {
+                    if i == 1 {
+                        let before = &(&points[0] + point) / 2.0;
+                        context.line_to(before.x as f64, before.y as f64);
+                    }
+                    let after = &(point + &points[i + 1]) / 2.0;
+                    context.quadratic_curve_to(
+                        point.x as f64,
+                        point.y as f64,
+                        after.x as f64,
+                        after.y as f64,
+                    );
+                }
+            }
+
+            Ok((0.0, 0.0))
+        }
+        // Drawn by `draw_sprite` instead.
+        Shape::Text { .. } => Ok((0.0, 0.0)),
+    }
+}
+
+// Traces straight lines joining `points` onto the current path.
+fn trace_points(context: &CanvasRenderingContext2d, points: &[Point]) {
+    for (i, point) in points.iter().enumerate() {
+        if i == 0 {
+            context.move_to(point.x as f64, point.y as f64);
+        } else {
+            context.line_to(point.x as f64, point.y as f64);
+        }
+    }
+}
+
+impl Backend for CanvasBackend {
+    fn look_at(&mut self, camera: &Camera) -> Result<(), RenderError> {
+        self.camera = camera.clone();
+        self.stale.set(true);
+
+        Ok(())
+    }
+
+    fn render(&mut self, layers: &[Layer], cell: &CellGeometry) -> Result<(), RenderError> {
+        let layer_names: Vec<String> = layers.iter().map(|layer| layer.name.clone()).collect();
+
+        // Layers above a changed one must be drawn over it again, so any change draws everything.
+        if self.stale.get()
+            || layer_names != self.drawn_layers
+            || layers.iter().any(|layer| layer.is_dirty())
+        {
+            self.redraw(layers, cell)?;
+            self.drawn_layers = layer_names;
+            self.stale.set(false);
+        }
+
+        Ok(())
+    }
+
+    fn set_atlas(&mut self, atlas: &TextureAtlas) -> Result<(), RenderError> {
+        self.atlas = atlas.clone();
+        self.stale.set(true);
+
+        Ok(())
+    }
+
+    fn client_point(&self, client: &Point) -> Result<Point, RenderError> {
+        let rect = self.canvas.get_bounding_client_rect();
+
+        let x = (client.x as f64 - rect.left()) / rect.width() * self.camera.view_width() as f64;
+        let y = (client.y as f64 - rect.top()) / rect.height() * self.camera.view_height() as f64;
+
+        Ok(Point::new(
+            x as f32 + self.camera.min_x(),
+            y as f32 + self.camera.min_y(),
+        ))
+    }
+}
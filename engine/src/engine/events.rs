@@ -0,0 +1,293 @@
+// Events on sprites are found by hit testing in Rust instead of by the browser. Handlers are
+// registered for the sprites of a layer, so they work with any backend and do not need the
+// sprite to have been drawn yet.
+
+use std::collections::HashMap;
+
+use super::cell::CellCoord;
+use super::layout::Point;
+use super::renderer::Hit;
+
+/// Distance in world pixels the mouse must move while pressed before it starts a drag.
+pub const DRAG_THRESHOLD: f32 = 4.0;
+
+/// Something that happened to a sprite. Points are in world space.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpriteEvent {
+    /// The mouse was pressed and released on the sprite without dragging.
+    Click { point: Point },
+    /// The mouse moved onto the sprite.
+    HoverEnter,
+    /// The mouse moved off the sprite.
+    HoverLeave,
+    /// The mouse was pressed on the sprite and moved past `DRAG_THRESHOLD`. `point` is where it
+    /// was pressed.
+    DragStart { point: Point },
+    /// The mouse moved while dragging the sprite.
+    Drag { point: Point },
+    /// The mouse was released after dragging the sprite.
+    DragEnd { point: Point },
+}
+
+type Handler = Box<dyn FnMut(&Hit, &SpriteEvent)>;
+
+/// Turns mouse input over the world into events on the sprites under the mouse.
+#[derive(Default)]
+pub struct EventDispatcher {
+    sprite_handlers: HashMap<(String, CellCoord), Vec<Handler>>,
+    layer_handlers: HashMap<String, Vec<Handler>>,
+    hovered: Option<Hit>,
+    // Sprite the mouse was pressed on, and where.
+    pressed: Option<(Hit, Point)>,
+    dragging: bool,
+}
+
+impl EventDispatcher {
+    pub fn new() -> EventDispatcher {
+        EventDispatcher::default()
+    }
+
+    /// Calls `handler` with the events on the sprite at `coord` of the layer `layer_name`. The
+    /// handler stays if the sprite is replaced by another at the same coord.
+    pub fn on<H>(&mut self, layer_name: &str, coord: CellCoord, handler: H)
+    where
+        H: 'static + FnMut(&Hit, &SpriteEvent),
+    {
+        self.sprite_handlers
+            .entry((layer_name.to_owned(), coord))
+            .or_default()
+            .push(Box::new(handler));
+    }
+
+    /// Calls `handler` with the events on every sprite of the layer `layer_name`.
+    pub fn on_layer<H>(&mut self, layer_name: &str, handler: H)
+    where
+        H: 'static + FnMut(&Hit, &SpriteEvent),
+    {
+        self.layer_handlers
+            .entry(layer_name.to_owned())
+            .or_default()
+            .push(Box::new(handler));
+    }
+
+    /// Removes the handlers of the sprite at `coord` of the layer `layer_name`.
+    pub fn remove(&mut self, layer_name: &str, coord: &CellCoord) {
+        self.sprite_handlers
+            .remove(&(layer_name.to_owned(), *coord));
+    }
+
+    /// Returns the sprite under the mouse.
+    pub fn hovered(&self) -> Option<&Hit> {
+        self.hovered.as_ref()
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    /// Handles the mouse being pressed at `point` over `hit`.
+    pub fn pointer_down(&mut self, point: Point, hit: Option<Hit>) {
+        self.hover(hit.clone());
+        self.pressed = hit.map(|hit| (hit, point));
+        self.dragging = false;
+    }
+
+    /// Handles the mouse moving to `point` over `hit`.
+    pub fn pointer_move(&mut self, point: Point, hit: Option<Hit>) {
+        self.hover(hit);
+
+        if let Some((pressed, start)) = self.pressed.clone() {
+            if !self.dragging {
+                let (dx, dy) = (point.x - start.x, point.y - start.y);
+                if (dx * dx + dy * dy).sqrt() < DRAG_THRESHOLD {
+                    return;
+                }
+
+                self.dragging = true;
+                self.dispatch(&pressed, &SpriteEvent::DragStart { point: start });
+            }
+
+            self.dispatch(&pressed, &SpriteEvent::Drag { point });
+        }
+    }
+
+    /// Handles the mouse being released at `point` over `hit`.
+    pub fn pointer_up(&mut self, point: Point, hit: Option<Hit>) {
+        self.hover(hit.clone());
+
+        if let Some((pressed, _)) = self.pressed.take() {
+            if self.dragging {
+                self.dispatch(&pressed, &SpriteEvent::DragEnd { point });
+            } else if hit.is_some_and(|hit| hit.is_same_entity(&pressed)) {
+                self.dispatch(&pressed, &SpriteEvent::Click { point });
+            }
+        }
+        self.dragging = false;
+    }
+
+    /// Handles the mouse leaving the world.
+    pub fn pointer_leave(&mut self) {
+        self.hover(None);
+    }
+
+    // Moves the hover to `hit`, leaving the sprite hovered before.
+    fn hover(&mut self, hit: Option<Hit>) {
+        let unchanged = match (&self.hovered, &hit) {
+            (Some(hovered), Some(hit)) => hovered.is_same_entity(hit),
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return;
+        }
+
+        if let Some(left) = self.hovered.take() {
+            self.dispatch(&left, &SpriteEvent::HoverLeave);
+        }
+        if let Some(entered) = &hit {
+            self.dispatch(entered, &SpriteEvent::HoverEnter);
+        }
+        self.hovered = hit;
+    }
+
+    fn dispatch(&mut self, hit: &Hit, event: &SpriteEvent) {
+        if let Some(handlers) = self
+            .sprite_handlers
+            .get_mut(&(hit.layer.clone(), hit.coord))
+        {
+            for handler in handlers {
+                handler(hit, event);
+            }
+        }
+
+        if let Some(handlers) = self.layer_handlers.get_mut(&hit.layer) {
+            for handler in handlers {
+                handler(hit, event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn hit(layer: &str, x: i32) -> Option<Hit> {
+        Some(Hit {
+            layer: layer.to_owned(),
+            coord: CellCoord::new(x, -x, 0),
+            sprite_id: format!("{}_{}", layer, x),
+        })
+    }
+
+    // Records every event of the layer `layer_name` as the id of its sprite and the event.
+    fn record(
+        dispatcher: &mut EventDispatcher,
+        layer_name: &str,
+    ) -> Rc<RefCell<Vec<(String, SpriteEvent)>>> {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&events);
+        dispatcher.on_layer(layer_name, move |hit, event| {
+            recorded
+                .borrow_mut()
+                .push((hit.sprite_id.clone(), event.clone()))
+        });
+
+        events
+    }
+
+    #[test]
+    fn click_01() {
+        let mut dispatcher = EventDispatcher::new();
+        let events = record(&mut dispatcher, "buildings");
+        let point = Point::new(1.0, 1.0);
+
+        dispatcher.pointer_down(point.clone(), hit("buildings", 0));
+        dispatcher.pointer_up(point.clone(), hit("buildings", 0));
+        // Releasing over another sprite is not a click.
+        dispatcher.pointer_down(point.clone(), hit("buildings", 0));
+        dispatcher.pointer_up(point.clone(), hit("buildings", 1));
+
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                ("buildings_0".to_owned(), SpriteEvent::HoverEnter),
+                ("buildings_0".to_owned(), SpriteEvent::Click { point }),
+                ("buildings_0".to_owned(), SpriteEvent::HoverLeave),
+                ("buildings_1".to_owned(), SpriteEvent::HoverEnter),
+            ]
+        );
+    }
+
+    #[test]
+    fn drag_01() {
+        let mut dispatcher = EventDispatcher::new();
+        let events = record(&mut dispatcher, "belts");
+        let start = Point::new(0.0, 0.0);
+
+        dispatcher.pointer_down(start.clone(), hit("belts", 0));
+        // Small moves do not start a drag.
+        dispatcher.pointer_move(Point::new(1.0, 1.0), hit("belts", 0));
+        assert!(!dispatcher.is_dragging());
+
+        dispatcher.pointer_move(Point::new(10.0, 0.0), None);
+        assert!(dispatcher.is_dragging());
+        dispatcher.pointer_up(Point::new(20.0, 0.0), None);
+
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                ("belts_0".to_owned(), SpriteEvent::HoverEnter),
+                ("belts_0".to_owned(), SpriteEvent::HoverLeave),
+                (
+                    "belts_0".to_owned(),
+                    SpriteEvent::DragStart { point: start }
+                ),
+                (
+                    "belts_0".to_owned(),
+                    SpriteEvent::Drag {
+                        point: Point::new(10.0, 0.0)
+                    }
+                ),
+                (
+                    "belts_0".to_owned(),
+                    SpriteEvent::DragEnd {
+                        point: Point::new(20.0, 0.0)
+                    }
+                ),
+            ]
+        );
+        assert!(!dispatcher.is_dragging());
+    }
+
+    #[test]
+    fn on_01() {
+        let mut dispatcher = EventDispatcher::new();
+        let coord = CellCoord::new(1, -1, 0);
+        let clicks = Rc::new(RefCell::new(0));
+        let counted = Rc::clone(&clicks);
+        dispatcher.on("buildings", coord, move |_, event| {
+            if let SpriteEvent::Click { .. } = event {
+                *counted.borrow_mut() += 1;
+            }
+        });
+
+        let point = Point::origin();
+        for x in 0..2 {
+            dispatcher.pointer_down(point.clone(), hit("buildings", x));
+            dispatcher.pointer_up(point.clone(), hit("buildings", x));
+        }
+        assert_eq!(*clicks.borrow(), 1);
+
+        dispatcher.remove("buildings", &coord);
+        dispatcher.pointer_down(point.clone(), hit("buildings", 1));
+        dispatcher.pointer_up(point.clone(), hit("buildings", 1));
+        assert_eq!(*clicks.borrow(), 1);
+
+        dispatcher.pointer_leave();
+        assert_eq!(dispatcher.hovered(), None);
+    }
+}
@@ -0,0 +1,779 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use super::atlas::TextureAtlas;
+use super::layout::Point;
+use super::renderer::{Backend, Camera, CellGeometry, DrawOrder, Layer, RenderError};
+use super::svg::{layer_start_tag, sprite_node, view_box, SvgNode};
+
+/// Renders into an in-memory scene instead of a document. The scene holds the same SVG that
+/// `SvgBackend` would draw, so it can be inspected or compared against a snapshot natively.
+pub struct HeadlessBackend {
+    scene: Rc<RefCell<Scene>>,
+    atlas: TextureAtlas,
+}
+
+impl HeadlessBackend {
+    pub fn new(width: i32, height: i32) -> HeadlessBackend {
+        HeadlessBackend {
+            scene: Rc::new(RefCell::new(Scene::new(&Camera::new(width, height)))),
+            atlas: TextureAtlas::new(),
+        }
+    }
+
+    /// Returns a handle to the scene. It stays valid after the backend is given to a viewport.
+    pub fn scene(&self) -> Rc<RefCell<Scene>> {
+        Rc::clone(&self.scene)
+    }
+}
+
+impl Backend for HeadlessBackend {
+    fn look_at(&mut self, camera: &Camera) -> Result<(), RenderError> {
+        self.scene.borrow_mut().view_box = view_box(camera);
+
+        Ok(())
+    }
+
+    fn render(&mut self, layers: &[Layer], cell: &CellGeometry) -> Result<(), RenderError> {
+        self.scene.borrow_mut().update(layers, cell, &self.atlas);
+
+        Ok(())
+    }
+
+    fn set_atlas(&mut self, atlas: &TextureAtlas) -> Result<(), RenderError> {
+        self.scene.borrow_mut().defs = Some(atlas.defs());
+        self.atlas = atlas.clone();
+
+        Ok(())
+    }
+
+    fn client_point(&self, _client: &Point) -> Result<Point, RenderError> {
+        Err(RenderError::new("headless backend does not receive events"))
+    }
+}
+
+/// Everything drawn by a `HeadlessBackend`.
+#[derive(Debug, Clone)]
+pub struct Scene {
+    view_box: String,
+    defs: Option<SvgNode>,
+    layers: Vec<SceneLayer>,
+    sprites_drawn: usize,
+    sprites_removed: usize,
+}
+
+#[derive(Debug, Clone)]
+struct SceneLayer {
+    name: String,
+    visible: bool,
+    opacity: f32,
+    sprites: HashMap<String, (DrawOrder, SvgNode)>,
+    // Sprite ids in the order they are drawn.
+    order: BTreeMap<DrawOrder, String>,
+}
+
+impl SceneLayer {
+    fn remove_sprite(&mut self, sprite_id: &str) -> bool {
+        match self.sprites.remove(sprite_id) {
+            Some((draw_order, _)) => {
+                self.order.remove(&draw_order);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Scene {
+    fn new(camera: &Camera) -> Scene {
+        Scene {
+            view_box: view_box(camera),
+            defs: None,
+            layers: Vec::new(),
+            sprites_drawn: 0,
+            sprites_removed: 0,
+        }
+    }
+
+    fn update(&mut self, layers: &[Layer], cell: &CellGeometry, atlas: &TextureAtlas) {
+        self.sprites_drawn = 0;
+        self.sprites_removed = 0;
+
+        // Remove the layers that no longer exist and put moved ones back in order.
+        let position = |name: &str| layers.iter().position(|layer| layer.name == name);
+        self.layers
+            .retain(|scene_layer| position(&scene_layer.name).is_some());
+        self.layers
+            .sort_by_key(|scene_layer| position(&scene_layer.name));
+
+        for (index, layer) in layers.iter().enumerate() {
+            let exists = self
+                .layers
+                .get(index)
+                .is_some_and(|scene_layer| scene_layer.name == layer.name);
+            if !exists {
+                self.layers.insert(
+                    index,
+                    SceneLayer {
+                        name: layer.name.clone(),
+                        visible: true,
+                        opacity: 1.0,
+                        sprites: HashMap::new(),
+                        order: BTreeMap::new(),
+                    },
+                );
+            }
+
+            let scene_layer = &mut self.layers[index];
+            scene_layer.visible = layer.is_visible();
+            scene_layer.opacity = layer.opacity();
+
+            for sprite_id in layer.removed_sprites() {
+                if scene_layer.remove_sprite(sprite_id) {
+                    self.sprites_removed += 1;
+                }
+            }
+
+            for sprite in layer.changed_sprites() {
+                scene_layer.remove_sprite(sprite.id());
+                scene_layer
+                    .order
+                    .insert(sprite.draw_order(), sprite.id().to_owned());
+                scene_layer.sprites.insert(
+                    sprite.id().to_owned(),
+                    (sprite.draw_order(), sprite_node(sprite, cell, atlas)),
+                );
+                self.sprites_drawn += 1;
+            }
+        }
+    }
+
+    pub fn view_box(&self) -> &str {
+        &self.view_box
+    }
+
+    /// Returns the names of the drawn layers, from bottom to top.
+    pub fn layer_names(&self) -> Vec<&str> {
+        self.layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect()
+    }
+
+    /// Returns the SVG drawn for the sprite `sprite_id` on the layer `layer_name`.
+    pub fn sprite(&self, layer_name: &str, sprite_id: &str) -> Option<&SvgNode> {
+        self.layers
+            .iter()
+            .find(|layer| layer.name == layer_name)
+            .and_then(|layer| layer.sprites.get(sprite_id))
+            .map(|(_, node)| node)
+    }
+
+    pub fn sprite_count(&self) -> usize {
+        self.layers.iter().map(|layer| layer.sprites.len()).sum()
+    }
+
+    /// Number of sprites drawn by the last render.
+    pub fn sprites_drawn(&self) -> usize {
+        self.sprites_drawn
+    }
+
+    /// Number of sprites removed by the last render.
+    pub fn sprites_removed(&self) -> usize {
+        self.sprites_removed
+    }
+
+    /// Returns the ids of the sprites drawn on the layer `layer_name`, from bottom to top.
+    pub fn sprite_ids(&self, layer_name: &str) -> Vec<&str> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.name == layer_name)
+            .flat_map(|layer| layer.order.values())
+            .map(|sprite_id| sprite_id.as_str())
+            .collect()
+    }
+
+    /// Writes the scene out as an SVG document, with each layer and sprite on its own line.
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!("<svg viewBox=\"{}\">\n", self.view_box);
+
+        if let Some(defs) = &self.defs {
+            svg.push_str(&format!("{}\n", defs));
+        }
+
+        for layer in &self.layers {
+            svg.push_str(&layer_start_tag(&layer.name, layer.visible, layer.opacity));
+            for sprite_id in layer.order.values() {
+                svg.push_str(&format!("{}\n", layer.sprites[sprite_id].1));
+            }
+            svg.push_str("</g>\n");
+        }
+
+        svg.push_str("</svg>\n");
+
+        svg
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::atlas::TextureDef;
+    use super::super::cell::CellCoord;
+    use super::super::layout::{HexLayout, HexOrientation, Rectangle};
+    use super::super::minimap::{MINIMAP_CELLS_LAYER, MINIMAP_FRAME_LAYER};
+    use super::super::renderer::Viewport;
+    use super::super::renderer::{Shape, Sprite, Texture};
+    use super::super::world::World;
+    use super::*;
+
+    fn world() -> (
+        World<super::super::cell::Hex, HexLayout>,
+        Rc<RefCell<Scene>>,
+    ) {
+        let layout = HexLayout::new(
+            HexOrientation::flat(),
+            Rectangle::new(10.0, 10.0),
+            Point::origin(),
+        );
+        let backend = HeadlessBackend::new(100, 80);
+        let scene = backend.scene();
+        let mut world = World::with_backend(Box::new(backend), layout, 100, 80);
+
+        world.viewport.insert_layer(0, Layer::new("ground"));
+        world.viewport.insert_layer(1, Layer::new("items"));
+
+        (world, scene)
+    }
+
+    fn square(id: &str, x: f32, color: &str) -> Sprite {
+        let mut texture = Texture::new();
+        texture.background_color = Some(color.to_owned());
+
+        Sprite::new(
+            id,
+            &Shape::Rectangle {
+                width: 4,
+                height: 2,
+            },
+            &Point::new(x, 0.0),
+            &texture,
+        )
+    }
+
+    #[test]
+    fn render_01() {
+        let (mut world, scene) = world();
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("a", 0.0, "red"));
+        layer.add_sprite(CellCoord::new(1, -1, 0), square("b", 15.0, "blue"));
+
+        world.render().unwrap();
+
+        assert_eq!(
+            scene.borrow().to_svg(),
+            "<svg viewBox=\"-50 -40 100 80\">\n\
+             <g id=\"ground\">\n\
+             </g>\n\
+             <g id=\"items\">\n\
+             <g id=\"a\" transform=\"translate(0,0)\">\
+             <rect width=\"4\" height=\"2\" x=\"-2\" y=\"-1\" style=\"fill:red;\"/></g>\n\
+             <g id=\"b\" transform=\"translate(15,0)\">\
+             <rect width=\"4\" height=\"2\" x=\"-2\" y=\"-1\" style=\"fill:blue;\"/></g>\n\
+             </g>\n\
+             </svg>\n"
+        );
+        assert_eq!(scene.borrow().sprites_drawn(), 2);
+    }
+
+    #[test]
+    fn atlas_01() {
+        let (mut world, scene) = world();
+        let mut atlas = TextureAtlas::new();
+        atlas.insert(
+            "belt",
+            TextureDef::Gradient {
+                stops: vec![(0.0, "gray".to_owned()), (1.0, "dimgray".to_owned())],
+            },
+        );
+        world.viewport.set_atlas(atlas).unwrap();
+
+        let layer = world.viewport.layer_mut("items").unwrap();
+        for (i, x) in [0.0, 15.0].iter().enumerate() {
+            let sprite = Sprite::new(
+                &i.to_string(),
+                &Shape::Circle { radius: 2 },
+                &Point::new(*x, 0.0),
+                &Texture::named("belt"),
+            );
+            layer.add_sprite(CellCoord::new(i as i32, -(i as i32), 0), sprite);
+        }
+
+        world.render().unwrap();
+
+        // Both sprites share the one definition.
+        assert_eq!(
+            scene.borrow().to_svg(),
+            "<svg viewBox=\"-50 -40 100 80\">\n\
+             <defs><linearGradient id=\"texture_belt\">\
+             <stop offset=\"0\" stop-color=\"gray\"/><stop offset=\"1\" stop-color=\"dimgray\"/>\
+             </linearGradient></defs>\n\
+             <g id=\"ground\">\n\
+             </g>\n\
+             <g id=\"items\">\n\
+             <g id=\"0\" transform=\"translate(0,0)\">\
+             <circle cx=\"0\" cy=\"0\" r=\"2\" style=\"fill:url(#texture_belt);\"/></g>\n\
+             <g id=\"1\" transform=\"translate(15,0)\">\
+             <circle cx=\"0\" cy=\"0\" r=\"2\" style=\"fill:url(#texture_belt);\"/></g>\n\
+             </g>\n\
+             </svg>\n"
+        );
+    }
+
+    #[test]
+    fn render_unchanged_01() {
+        let (mut world, scene) = world();
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("a", 0.0, "red"));
+
+        world.render().unwrap();
+        let first = scene.borrow().to_svg();
+        world.render().unwrap();
+
+        assert_eq!(scene.borrow().sprites_drawn(), 0);
+        assert_eq!(scene.borrow().to_svg(), first);
+    }
+
+    #[test]
+    fn render_changed_01() {
+        let (mut world, scene) = world();
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("a", 0.0, "red"));
+        layer.add_sprite(CellCoord::new(1, -1, 0), square("b", 15.0, "blue"));
+        world.render().unwrap();
+
+        // Borrowing a sprite without changing it draws nothing.
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.sprite_mut(&CellCoord::new(0, 0, 0)).unwrap();
+        world.render().unwrap();
+        assert_eq!(scene.borrow().sprites_drawn(), 0);
+
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer
+            .sprite_mut(&CellCoord::new(1, -1, 0))
+            .unwrap()
+            .set_position(&Point::new(-15.0, 5.0));
+        world.render().unwrap();
+
+        let scene = scene.borrow();
+        assert_eq!(scene.sprites_drawn(), 1);
+        assert_eq!(
+            scene.sprite("items", "b").unwrap().to_string(),
+            "<g id=\"b\" transform=\"translate(-15,5)\">\
+             <rect width=\"4\" height=\"2\" x=\"-2\" y=\"-1\" style=\"fill:blue;\"/></g>"
+        );
+    }
+
+    #[test]
+    fn remove_sprite_01() {
+        let (mut world, scene) = world();
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("a", 0.0, "red"));
+        layer.add_sprite(CellCoord::new(1, -1, 0), square("b", 15.0, "blue"));
+        world.render().unwrap();
+
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.remove_sprite("a".to_owned());
+        world.render().unwrap();
+
+        let scene = scene.borrow();
+        assert_eq!(scene.sprites_drawn(), 0);
+        assert_eq!(scene.sprites_removed(), 1);
+        assert!(scene.sprite("items", "a").is_none());
+        assert!(scene.sprite("items", "b").is_some());
+    }
+
+    #[test]
+    fn clear_layer_01() {
+        let (mut world, scene) = world();
+        let layer = world.viewport.layer_mut("ground").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("g", 0.0, "green"));
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("a", 0.0, "red"));
+        world.render().unwrap();
+
+        world.clear_layer("items");
+
+        let scene = scene.borrow();
+        assert_eq!(scene.sprites_removed(), 1);
+        assert_eq!(scene.sprite_count(), 1);
+        assert!(scene.sprite("ground", "g").is_some());
+    }
+
+    #[test]
+    fn layers_01() {
+        let (mut world, scene) = world();
+        world.render().unwrap();
+        assert_eq!(scene.borrow().layer_names(), vec!["ground", "items"]);
+
+        world.viewport.insert_layer(1, Layer::new("buildings"));
+        world.render().unwrap();
+        assert_eq!(
+            scene.borrow().layer_names(),
+            vec!["ground", "buildings", "items"]
+        );
+
+        world.viewport.remove_layer("ground");
+        assert_eq!(scene.borrow().layer_names(), vec!["buildings", "items"]);
+    }
+
+    #[test]
+    fn move_layer_01() {
+        let (mut world, scene) = world();
+        let layer = world.viewport.layer_mut("ground").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("a", 0.0, "red"));
+        world.render().unwrap();
+
+        world.viewport.move_layer("ground", 5).unwrap();
+        assert_eq!(scene.borrow().layer_names(), vec!["items", "ground"]);
+        // Moving a layer does not draw its sprites again.
+        assert_eq!(scene.borrow().sprites_drawn(), 0);
+        assert_eq!(scene.borrow().sprite_ids("ground"), vec!["a"]);
+
+        assert!(world.viewport.move_layer("water", 0).is_err());
+    }
+
+    #[test]
+    fn layer_style_01() {
+        let (mut world, scene) = world();
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("a", 0.0, "red"));
+        world.render().unwrap();
+
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.set_visible(false);
+        layer.set_opacity(0.5);
+        world.render().unwrap();
+
+        assert_eq!(
+            scene.borrow().to_svg(),
+            "<svg viewBox=\"-50 -40 100 80\">\n\
+             <g id=\"ground\">\n\
+             </g>\n\
+             <g id=\"items\" opacity=\"0.5\" display=\"none\">\n\
+             <g id=\"a\" transform=\"translate(0,0)\">\
+             <rect width=\"4\" height=\"2\" x=\"-2\" y=\"-1\" style=\"fill:red;\"/></g>\n\
+             </g>\n\
+             </svg>\n"
+        );
+        assert_eq!(scene.borrow().sprites_drawn(), 0);
+
+        // Restyling a layer only changes it once.
+        world.render().unwrap();
+        assert!(!world.viewport.layer("items").unwrap().is_dirty());
+    }
+
+    #[test]
+    fn sprite_visible_01() {
+        let (mut world, scene) = world();
+        let coord = CellCoord::new(0, 0, 0);
+        let mut hidden = square("a", 0.0, "red");
+        hidden.visible(false);
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(coord, hidden);
+        layer.add_sprite(CellCoord::new(1, -1, 0), square("b", 15.0, "blue"));
+        world.render().unwrap();
+
+        assert_eq!(scene.borrow().sprite_ids("items"), vec!["b"]);
+
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.sprite_mut(&coord).unwrap().visible(true);
+        world.render().unwrap();
+        assert_eq!(scene.borrow().sprite_ids("items"), vec!["a", "b"]);
+
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.sprite_mut(&coord).unwrap().visible(false);
+        world.render().unwrap();
+        assert_eq!(scene.borrow().sprite_ids("items"), vec!["b"]);
+        assert_eq!(scene.borrow().sprites_removed(), 1);
+    }
+
+    #[test]
+    fn hit_test_01() {
+        let (mut world, _scene) = world();
+        // Nothing is hit before the first render.
+        assert_eq!(world.hit_test(&Point::origin()), None);
+
+        let ground = world.viewport.layer_mut("ground").unwrap();
+        ground.add_sprite(CellCoord::new(0, 0, 0), square("g", 0.0, "green"));
+        let items = world.viewport.layer_mut("items").unwrap();
+        items.add_sprite(CellCoord::new(0, 0, 0), square("a", 0.0, "red"));
+        let mut above = square("b", 1.0, "blue");
+        above.set_z_index(1);
+        items.add_sprite(CellCoord::new(1, -1, 0), above);
+        world.render().unwrap();
+
+        // The sprite drawn on top is hit.
+        let hit = world.hit_test(&Point::new(0.5, 0.0)).unwrap();
+        assert_eq!(hit.layer, "items");
+        assert_eq!(hit.sprite_id, "b");
+        assert_eq!(hit.coord, CellCoord::new(1, -1, 0));
+        assert_eq!(world.hit_test(&Point::new(50.0, 0.0)), None);
+
+        // Layers that are hidden or not interactive are skipped.
+        world
+            .viewport
+            .layer_mut("items")
+            .unwrap()
+            .set_interactive(false);
+        let hit = world.hit_test(&Point::new(0.5, 0.0)).unwrap();
+        assert_eq!(hit.sprite_id, "g");
+
+        world
+            .viewport
+            .layer_mut("ground")
+            .unwrap()
+            .set_visible(false);
+        assert_eq!(world.hit_test(&Point::new(0.5, 0.0)), None);
+    }
+
+    #[test]
+    fn look_at_01() {
+        let (mut world, scene) = world();
+        world.look_at(&Point::new(20.0, -10.0)).unwrap();
+
+        assert_eq!(scene.borrow().view_box(), "-30 -50 100 80");
+    }
+
+    #[test]
+    fn export_svg_01() {
+        let (mut world, scene) = world();
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("a", 0.0, "red"));
+        world.look_at(&Point::new(10.0, 0.0)).unwrap();
+
+        // Exporting does not need a render.
+        let svg = world.export_svg();
+        assert_eq!(scene.borrow().sprite_count(), 0);
+
+        world.render().unwrap();
+        let rendered = scene.borrow().to_svg();
+        assert_eq!(
+            svg,
+            rendered.replace(
+                "<svg ",
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"80\" "
+            )
+        );
+    }
+
+    #[test]
+    fn zoom_01() {
+        let (mut world, scene) = world();
+        world.zoom(2.0).unwrap();
+        assert_eq!(scene.borrow().view_box(), "-25 -20 50 40");
+
+        // Zooming out about a point moves the center away from it.
+        world.zoom_at(0.5, &Point::new(10.0, 10.0)).unwrap();
+        assert_eq!(scene.borrow().view_box(), "-60 -50 100 80");
+    }
+
+    #[test]
+    fn map_bounds_01() {
+        let (mut world, scene) = world();
+        let coords = vec![CellCoord::new(0, 0, 0), CellCoord::new(2, -1, -1)];
+
+        let bounds = world.map_bounds(&coords).unwrap();
+        assert_eq!(bounds.min.x, -10.0);
+        assert_eq!(bounds.max.x, 40.0);
+        assert_eq!(bounds.min.y, -bounds.max.y);
+
+        // The map is smaller than the view, so the camera is kept on its center.
+        world.set_map_bounds(&coords).unwrap();
+        world.pan(&Point::new(100.0, 100.0)).unwrap();
+        let camera = world.camera(world.active_camera());
+        assert_eq!(camera.position, bounds.center());
+        assert_eq!(
+            scene.borrow().view_box(),
+            format!("{} {} 100 80", camera.min_x(), camera.min_y())
+        );
+    }
+
+    #[test]
+    fn views_01() {
+        let (mut world, scene) = world();
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("a", 0.0, "red"));
+
+        let backend = HeadlessBackend::new(50, 40);
+        let split_scene = backend.scene();
+        let mut viewport = Viewport::with_backend(Box::new(backend));
+        viewport.insert_layer(0, Layer::new("items"));
+        let camera = world.add_camera(Camera::new(50, 40));
+        let view = world.add_view(viewport, camera).unwrap();
+
+        world.camera_mut(camera).look_at(&Point::new(10.0, 10.0));
+        world.pan(&Point::new(5.0, 0.0)).unwrap();
+        world.render().unwrap();
+
+        // Each view shows its own camera.
+        assert_eq!(scene.borrow().view_box(), "-45 -40 100 80");
+        assert_eq!(split_scene.borrow().view_box(), "-15 -10 50 40");
+        assert_eq!(world.view(view).camera(), camera);
+
+        // Switching the active camera changes the main viewport only.
+        world.set_active_camera(camera).unwrap();
+        assert_eq!(scene.borrow().view_box(), "-15 -10 50 40");
+        assert_eq!(scene.borrow().sprite_count(), 1);
+        assert_eq!(split_scene.borrow().sprite_count(), 0);
+    }
+
+    #[test]
+    fn minimap_01() {
+        let (mut world, _) = world();
+        let coords = vec![CellCoord::new(0, 0, 0), CellCoord::new(2, -1, -1)];
+
+        let backend = HeadlessBackend::new(50, 50);
+        let minimap_scene = backend.scene();
+        world
+            .add_minimap(Box::new(backend), 50, 50, &coords, "gray")
+            .unwrap();
+
+        {
+            let minimap_scene = minimap_scene.borrow();
+            assert_eq!(
+                minimap_scene.layer_names(),
+                vec![MINIMAP_CELLS_LAYER, MINIMAP_FRAME_LAYER]
+            );
+            assert!(minimap_scene
+                .sprite(MINIMAP_CELLS_LAYER, "minimap_2,-1,-1")
+                .is_some());
+            // The minimap shows the whole map, which is 50 pixels wide.
+            assert_eq!(minimap_scene.view_box(), "-10 -25 50 50");
+        }
+
+        world.pan(&Point::new(15.0, 0.0)).unwrap();
+
+        let minimap_scene = minimap_scene.borrow();
+        assert_eq!(minimap_scene.sprites_drawn(), 1);
+        assert_eq!(
+            minimap_scene
+                .sprite(MINIMAP_FRAME_LAYER, "minimap_camera_frame")
+                .unwrap()
+                .to_string(),
+            "<g id=\"minimap_camera_frame\" transform=\"translate(15,0)\">\
+             <rect width=\"100\" height=\"80\" x=\"-50\" y=\"-40\" \
+             style=\"fill-opacity: 0.0;stroke:gray;stroke-width:2;\"/></g>"
+        );
+    }
+
+    #[test]
+    fn cull_01() {
+        let (mut world, scene) = world();
+        world.look_at(&Point::origin()).unwrap();
+
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("near", 0.0, "red"));
+        layer.add_sprite(CellCoord::new(40, -20, -20), square("far", 1000.0, "blue"));
+        world.render().unwrap();
+
+        assert!(scene.borrow().sprite("items", "near").is_some());
+        assert!(scene.borrow().sprite("items", "far").is_none());
+
+        // Panning streams sprites in and out of the scene.
+        world.look_at(&Point::new(1000.0, 0.0)).unwrap();
+
+        let scene = scene.borrow();
+        assert_eq!(scene.sprites_drawn(), 1);
+        assert_eq!(scene.sprites_removed(), 1);
+        assert!(scene.sprite("items", "near").is_none());
+        assert!(scene.sprite("items", "far").is_some());
+        assert_eq!(world.viewport.layer("items").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn cull_02() {
+        let (mut world, scene) = world();
+        world.look_at(&Point::origin()).unwrap();
+
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(CellCoord::new(0, 0, 0), square("a", 0.0, "red"));
+        world.render().unwrap();
+
+        // Moving a sprite out of view removes it, and moving it back draws it again.
+        let layer = world.viewport.layer_mut("items").unwrap();
+        let coord = CellCoord::new(0, 0, 0);
+        layer
+            .sprite_mut(&coord)
+            .unwrap()
+            .set_position(&Point::new(0.0, 1000.0));
+        world.render().unwrap();
+        assert_eq!(scene.borrow().sprites_removed(), 1);
+        assert_eq!(scene.borrow().sprite_count(), 0);
+
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer
+            .sprite_mut(&coord)
+            .unwrap()
+            .set_position(&Point::new(10.0, 0.0));
+        world.render().unwrap();
+        assert_eq!(scene.borrow().sprites_drawn(), 1);
+        assert_eq!(scene.borrow().sprite_count(), 1);
+    }
+
+    #[test]
+    fn z_index_01() {
+        let (mut world, scene) = world();
+        let layer = world.viewport.layer_mut("items").unwrap();
+        let mut top = square("top", 0.0, "red");
+        top.set_z_index(1);
+        layer.add_sprite(CellCoord::new(0, 0, 0), top);
+        layer.add_sprite(CellCoord::new(1, -1, 0), square("b", 0.0, "blue"));
+        layer.add_sprite(CellCoord::new(2, -2, 0), square("c", 0.0, "green"));
+        world.render().unwrap();
+
+        assert_eq!(scene.borrow().sprite_ids("items"), vec!["b", "c", "top"]);
+
+        // Replacing a sprite keeps its place, and raising one moves it up.
+        let layer = world.viewport.layer_mut("items").unwrap();
+        layer.add_sprite(CellCoord::new(1, -1, 0), square("b2", 0.0, "blue"));
+        layer
+            .sprite_mut(&CellCoord::new(2, -2, 0))
+            .unwrap()
+            .set_z_index(2);
+        world.render().unwrap();
+
+        assert_eq!(scene.borrow().sprite_ids("items"), vec!["b2", "top", "c"]);
+        assert_eq!(world.export_svg().matches("<g id=").count(), 5);
+    }
+
+    #[test]
+    fn direction_rotation_01() {
+        let (world, _) = world();
+        let rotations: Vec<f32> = (0..6)
+            .map(|direction| world.direction_rotation(direction).round())
+            .collect();
+
+        // Flat hexes have neighbours every 60 degrees, starting below the right.
+        assert_eq!(rotations, vec![30.0, -30.0, -90.0, -150.0, 150.0, 90.0]);
+    }
+
+    #[test]
+    fn cell_01() {
+        let (mut world, scene) = world();
+        let layer = world.viewport.layer_mut("ground").unwrap();
+        layer.add_sprite(
+            CellCoord::new(0, 0, 0),
+            Sprite::new("c", &Shape::Cell, &Point::origin(), &Texture::new()),
+        );
+        world.render().unwrap();
+
+        let scene = scene.borrow();
+        let cell = scene.sprite("ground", "c").unwrap();
+        let polygon = &cell.children[0];
+        assert_eq!(polygon.name, "polygon");
+        assert_eq!(polygon.attributes[0].0, "points");
+        // A hex has six corners.
+        assert_eq!(polygon.attributes[0].1.split_whitespace().count(), 6);
+    }
+}
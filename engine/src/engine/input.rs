@@ -0,0 +1,239 @@
+// Input bindings map the keys and mouse buttons the player presses to the actions of the game, so
+// the controls can be loaded from a file and changed while playing.
+
+use std::collections::BTreeMap;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use ron::ser::{to_string_pretty, PrettyConfig};
+
+use super::renderer::RenderError;
+
+use web_sys::{KeyboardEvent, MouseEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+impl MouseButton {
+    /// Returns the button numbered `button` by `MouseEvent::button()`.
+    pub fn from_button(button: i16) -> Option<MouseButton> {
+        match button {
+            0 => Some(MouseButton::Left),
+            1 => Some(MouseButton::Middle),
+            2 => Some(MouseButton::Right),
+            _ => None,
+        }
+    }
+}
+
+/// A key or mouse button that can be bound to an action.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Input {
+    /// A key named as by `KeyboardEvent::key()`, such as `"a"` or `"ArrowLeft"`.
+    Key(String),
+    Mouse(MouseButton),
+}
+
+impl Input {
+    /// Returns the input for the key `key`. Letters are lowercased so a binding works whether or
+    /// not shift is held.
+    pub fn key(key: &str) -> Input {
+        if key.chars().count() == 1 {
+            Input::Key(key.to_lowercase())
+        } else {
+            Input::Key(key.to_owned())
+        }
+    }
+
+    /// Returns the input as `Input::key` would have made it, so inputs written by hand, such as
+    /// in a bindings manifest, match the ones read from key events.
+    fn normalized(self) -> Input {
+        match self {
+            Input::Key(key) => Input::key(&key),
+            mouse => mouse,
+        }
+    }
+
+    pub fn from_key_event(event: &KeyboardEvent) -> Input {
+        Input::key(&event.key())
+    }
+
+    /// Returns the input for the button of `event`, or `None` for buttons that cannot be bound.
+    pub fn from_mouse_event(event: &MouseEvent) -> Option<Input> {
+        MouseButton::from_button(event.button()).map(Input::Mouse)
+    }
+}
+
+/// Maps inputs to the actions `A` they trigger. An input triggers at most one action, but an
+/// action can be triggered by many inputs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputBindings<A> {
+    // Ordered by input so saved bindings are always written in the same order.
+    bindings: BTreeMap<Input, A>,
+}
+
+impl<A> Default for InputBindings<A> {
+    fn default() -> InputBindings<A> {
+        InputBindings {
+            bindings: BTreeMap::new(),
+        }
+    }
+}
+
+impl<A: PartialEq> InputBindings<A> {
+    pub fn new() -> InputBindings<A> {
+        InputBindings::default()
+    }
+
+    /// Loads bindings from a RON manifest such as `(bindings: {Key("r"): Rotate})`.
+    pub fn from_ron(manifest: &str) -> Result<InputBindings<A>, RenderError>
+    where
+        A: DeserializeOwned,
+    {
+        let bindings: InputBindings<A> = ron::de::from_str(manifest).map_err(|error| {
+            RenderError::new(format!("invalid input bindings manifest: {}", error).as_str())
+        })?;
+
+        Ok(InputBindings {
+            bindings: bindings
+                .bindings
+                .into_iter()
+                .map(|(input, action)| (input.normalized(), action))
+                .collect(),
+        })
+    }
+
+    /// Writes the bindings as a RON manifest that `from_ron` can load, such as to save the
+    /// bindings after they were changed.
+    pub fn to_ron(&self) -> Result<String, RenderError>
+    where
+        A: Serialize,
+    {
+        to_string_pretty(self, PrettyConfig::new()).map_err(|error| {
+            RenderError::new(format!("failed to write input bindings: {}", error).as_str())
+        })
+    }
+
+    /// Binds `input` to `action`, returning the action it was bound to before.
+    pub fn bind(&mut self, input: Input, action: A) -> Option<A> {
+        self.bindings.insert(input.normalized(), action)
+    }
+
+    /// Removes the binding of `input`, returning the action it was bound to.
+    pub fn unbind(&mut self, input: &Input) -> Option<A> {
+        self.bindings.remove(input)
+    }
+
+    /// Makes `input` the only input bound to `action`.
+    pub fn rebind(&mut self, action: A, input: Input) {
+        self.bindings.retain(|_, bound| *bound != action);
+        self.bindings.insert(input.normalized(), action);
+    }
+
+    /// Returns the action triggered by `input`.
+    pub fn action(&self, input: &Input) -> Option<&A> {
+        self.bindings.get(input)
+    }
+
+    /// Returns the inputs bound to `action`.
+    pub fn inputs(&self, action: &A) -> Vec<&Input> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(input, _)| input)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    enum Action {
+        Rotate,
+        Cancel,
+        Pan,
+    }
+
+    #[test]
+    fn from_ron_01() {
+        let bindings: InputBindings<Action> = InputBindings::from_ron(
+            r#"(
+                bindings: {
+                    Key("r"): Rotate,
+                    Key("Escape"): Cancel,
+                    Mouse(Right): Cancel,
+                },
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(bindings.action(&Input::key("R")), Some(&Action::Rotate));
+        assert_eq!(
+            bindings.action(&Input::Mouse(MouseButton::Right)),
+            Some(&Action::Cancel)
+        );
+        assert_eq!(bindings.action(&Input::Mouse(MouseButton::Left)), None);
+        assert_eq!(
+            bindings.inputs(&Action::Cancel),
+            vec![
+                &Input::Key("Escape".to_owned()),
+                &Input::Mouse(MouseButton::Right)
+            ]
+        );
+        assert_eq!(
+            InputBindings::<Action>::from_ron(&bindings.to_ron().unwrap()).unwrap(),
+            bindings
+        );
+        assert!(InputBindings::<Action>::from_ron("(bindings: {Key(\"r\"): Jump})").is_err());
+    }
+
+    #[test]
+    fn from_ron_02() {
+        // Upper case letters match the key events, which are lowercased.
+        let mut bindings: InputBindings<Action> =
+            InputBindings::from_ron(r#"(bindings: {Key("R"): Rotate, Key("Escape"): Cancel})"#)
+                .unwrap();
+        assert_eq!(bindings.action(&Input::key("r")), Some(&Action::Rotate));
+        assert_eq!(
+            bindings.action(&Input::key("Escape")),
+            Some(&Action::Cancel)
+        );
+
+        bindings.bind(Input::Key("P".to_owned()), Action::Pan);
+        assert_eq!(bindings.action(&Input::key("p")), Some(&Action::Pan));
+        bindings.rebind(Action::Cancel, Input::Key("C".to_owned()));
+        assert_eq!(bindings.action(&Input::key("c")), Some(&Action::Cancel));
+    }
+
+    #[test]
+    fn rebind_01() {
+        let mut bindings = InputBindings::new();
+        bindings.bind(Input::key("a"), Action::Pan);
+        bindings.bind(Input::key("ArrowLeft"), Action::Pan);
+        assert_eq!(bindings.bind(Input::key("r"), Action::Rotate), None);
+
+        // Binding an input again replaces its action.
+        assert_eq!(
+            bindings.bind(Input::key("r"), Action::Cancel),
+            Some(Action::Rotate)
+        );
+        assert_eq!(bindings.inputs(&Action::Rotate), Vec::<&Input>::new());
+
+        bindings.rebind(Action::Pan, Input::Mouse(MouseButton::Middle));
+        assert_eq!(
+            bindings.inputs(&Action::Pan),
+            vec![&Input::Mouse(MouseButton::Middle)]
+        );
+
+        assert_eq!(bindings.unbind(&Input::key("r")), Some(Action::Cancel));
+        assert_eq!(bindings.action(&Input::key("r")), None);
+        assert_eq!(MouseButton::from_button(3), None);
+    }
+}
@@ -11,7 +11,12 @@ pub trait Layout {
     fn pixel_to_cell(&self, point: &Point) -> Self::C;
     fn cell_corner_offset(&self, corner: i32) -> Point;
     fn polygon_corners(&self, cell: &Self::C) -> Vec<Point>;
+    /// Returns the middle of the edge opposite the neighbor in `direction`, about the center of
+    /// the cell.
     fn polygon_edge_center(&self, cell: &Self::C, direction: i32) -> Point;
+    /// Returns the middle of the edge shared with the neighbor in `direction`, about the center
+    /// of the cell.
+    fn shared_edge_center(&self, cell: &Self::C, direction: i32) -> Point;
     fn origin(&self) -> &Point;
 }
 
@@ -199,7 +204,39 @@ impl Layout for HexLayout {
         &(cell_center - neighbor_center) / 2.0
     }
 
+    fn shared_edge_center(&self, cell: &Hex, direction: i32) -> Point {
+        let opposite = cell.opposite_direction(direction);
+        self.polygon_edge_center(cell, opposite)
+    }
+
     fn origin(&self) -> &Point {
         &self.origin
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn edge_center_01() {
+        let layout = HexLayout::new(
+            HexOrientation::flat(),
+            Rectangle::new(10.0, 10.0),
+            Point::new(100.0, 100.0),
+        );
+        let cell = Hex::new(1.0, -1.0, 0.0);
+
+        // The neighbor in direction 0 is to the lower right of a flat cell.
+        let shared = layout.shared_edge_center(&cell, 0);
+        assert_eq!(shared.x, 7.5);
+        assert!((shared.y - 4.33).abs() < 0.01);
+
+        // `polygon_edge_center` returns the edge on the other side of the cell.
+        assert_eq!(
+            layout.polygon_edge_center(&cell, 0),
+            Point::new(-7.5, -shared.y)
+        );
+        assert_eq!(layout.polygon_edge_center(&cell, 3), shared);
+    }
+}
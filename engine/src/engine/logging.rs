@@ -1,4 +1,5 @@
-//use log;
+// Messages go to the browser console. Native builds, such as tests, have no console and use `log`
+// instead.
 
 use wasm_bindgen::prelude::wasm_bindgen;
 
@@ -11,30 +12,50 @@ pub fn alert_js(message: String) {
     alert(message.as_str());
 }
 
+#[cfg(target_arch = "wasm32")]
 pub fn debug(message: String) {
     use web_sys::console;
 
-    //log::debug!("{:?}", message);
     console::debug_1(&message.into());
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+pub fn debug(message: String) {
+    log::debug!("{}", message);
+}
+
+#[cfg(target_arch = "wasm32")]
 pub fn info(message: String) {
     use web_sys::console;
 
-    //log::info!("{:?}", message);
     console::info_1(&message.into());
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+pub fn info(message: String) {
+    log::info!("{}", message);
+}
+
+#[cfg(target_arch = "wasm32")]
 pub fn warn(message: String) {
     use web_sys::console;
 
-    //log::warn!("{:?}", message);
     console::warn_1(&message.into());
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+pub fn warn(message: String) {
+    log::warn!("{}", message);
+}
+
+#[cfg(target_arch = "wasm32")]
 pub fn error(message: String) {
     use web_sys::console;
 
-    //log::error!("{:?}", message);
     console::error_1(&message.into());
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn error(message: String) {
+    log::error!("{}", message);
+}
@@ -0,0 +1,77 @@
+// A minimap is an overview of the whole map drawn in its own viewport. Cells are drawn as plain
+// colored cells and a frame shows the part of the world seen by the active camera.
+
+use super::cell::{Cell, CellCoord};
+use super::layout::Layout;
+use super::renderer::{Camera, Layer, Shape, Sprite, Texture, TextureBorder};
+
+pub const MINIMAP_CELLS_LAYER: &str = "minimap_cells";
+pub const MINIMAP_FRAME_LAYER: &str = "minimap_frame";
+
+const FRAME_SPRITE_ID: &str = "minimap_camera_frame";
+// Width of the frame border in minimap pixels.
+const FRAME_BORDER_WIDTH: f32 = 2.0;
+
+/// Creates a layer with a cell filled with `color` for each of `coords`.
+pub fn cells_layer<'a, C, L, I>(layout: &L, coords: I, color: &str) -> Layer
+where
+    C: Cell,
+    L: Layout<C = C>,
+    I: IntoIterator<Item = &'a CellCoord>,
+{
+    let mut layer = Layer::new(MINIMAP_CELLS_LAYER);
+
+    let mut texture = Texture::new();
+    texture.background_color = Some(color.to_owned());
+
+    for coord in coords {
+        let sprite_id = format!("minimap_{}", String::from(coord));
+        let position = layout.pixel_from_coord(coord);
+
+        layer.add_sprite(
+            *coord,
+            Sprite::new(&sprite_id, &Shape::Cell, &position, &texture),
+        );
+    }
+
+    layer
+}
+
+/// Creates the layer with the frame showing what `camera` sees.
+pub fn frame_layer(camera: &Camera, minimap_camera: &Camera, color: &str) -> Layer {
+    let mut layer = Layer::new(MINIMAP_FRAME_LAYER);
+
+    let mut texture = Texture::new();
+    // Keep the border the same width on the minimap whatever its zoom.
+    let border_width = (FRAME_BORDER_WIDTH / minimap_camera.zoom())
+        .round()
+        .max(1.0);
+    texture.border = Some(TextureBorder::new(border_width as i32, color));
+
+    layer.add_sprite(
+        CellCoord::new(0, 0, 0),
+        Sprite::new(
+            FRAME_SPRITE_ID,
+            &frame_shape(camera),
+            &camera.position,
+            &texture,
+        ),
+    );
+
+    layer
+}
+
+/// Moves the frame in `layer` to what `camera` sees.
+pub fn update_frame(layer: &mut Layer, camera: &Camera) {
+    if let Some(frame) = layer.sprite_mut(&CellCoord::new(0, 0, 0)) {
+        frame.set_shape(&frame_shape(camera));
+        frame.set_position(&camera.position);
+    }
+}
+
+fn frame_shape(camera: &Camera) -> Shape {
+    Shape::Rectangle {
+        width: camera.view_width().round() as i32,
+        height: camera.view_height().round() as i32,
+    }
+}
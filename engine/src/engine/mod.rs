@@ -1,15 +1,34 @@
+mod animation;
+mod atlas;
+mod canvas;
 mod cell;
+mod events;
+// Renders in memory so rendering can be tested natively.
+#[cfg(test)]
+mod headless;
+mod input;
 mod layout;
 mod logging;
+mod minimap;
 mod renderer;
 pub mod rng;
+mod spatial;
+mod svg;
+mod touch;
 mod world;
 
+pub use animation::{Animation, Easing, Keyframe};
+pub use atlas::TextureAtlas;
+pub use canvas::CanvasBackend;
 pub use cell::{Cell, CellCoord, Hex};
+pub use events::{EventDispatcher, SpriteEvent};
+pub use input::{Input, InputBindings};
 pub use layout::{HexLayout, HexOrientation, Layout, Point, Rectangle};
 pub use logging::{alert_js, debug, error, info, warn};
 pub use renderer::{
-    get_body, get_target, Camera, Layer, Renderable, Shape, Size, Sprite, Texture, TextureBorder,
-    UserEvent,
+    add_event, add_key_event, add_mouse_event, add_pointer_event, add_touch_event,
+    add_wheel_event, get_body, get_target, on_animation_frame, Backend, Bounds, Camera,
+    CellGeometry, DrawOrder, EventListener, Hit, Layer, RenderError, Shape, Size, Sprite, Stroke,
+    TextAnchor, Texture, TextureBorder, UserEvent, CULL_MARGIN, MAX_ZOOM, MIN_ZOOM, ZOOM_STEP,
 };
-pub use world::{shortest_path, World};
+pub use world::{shortest_path, World, TICKS_PER_SECOND};
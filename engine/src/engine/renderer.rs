@@ -1,20 +1,28 @@
 use std::cell::{Ref, RefCell};
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::From;
 use std::error::Error;
 use std::fmt;
+use std::rc::Rc;
 
 use crate::engine;
 use crate::engine::{
-    debug, error, Cell, CellCoord, Hex, HexLayout, HexOrientation, Layout, Point, Rectangle,
+    error, Cell, CellCoord, Hex, HexLayout, HexOrientation, Layout, Point, Rectangle,
 };
 
 // use super::belt::BeltView;
 // use super::building::{BuildingState, BuildingView};
 // use super::world::{GameStateView, WORLD};
 
-use web_sys::{Document, Element, Event, KeyEvent, MouseEvent, SvgElement, SvgsvgElement};
+use super::atlas::TextureAtlas;
+use super::spatial::SpatialIndex;
+use super::svg::{export_svg, SvgBackend};
+
+use web_sys::{
+    AddEventListenerOptions, Document, Element, Event, KeyboardEvent, MouseEvent, PointerEvent,
+    TouchEvent, WheelEvent,
+};
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::*;
@@ -37,6 +45,28 @@ pub enum UserEvent {
     MouseOut,
     KeyDown,
     KeyUp,
+    Wheel,
+    PointerDown,
+    PointerMove,
+    PointerUp,
+    PointerCancel,
+    TouchStart,
+    TouchMove,
+    TouchEnd,
+    TouchCancel,
+    ContextMenu,
+}
+
+impl UserEvent {
+    /// Returns the `passive` option to listen for the event with, or `None` to leave it to the
+    /// browser. Browsers make wheel and touch listeners on the window, document and body passive
+    /// by default, and passive listeners cannot stop the page from scrolling.
+    fn passive(&self) -> Option<bool> {
+        match self {
+            UserEvent::Wheel | UserEvent::TouchStart | UserEvent::TouchMove => Some(false),
+            _ => None,
+        }
+    }
 }
 
 impl From<&UserEvent> for &str {
@@ -50,48 +80,181 @@ impl From<&UserEvent> for &str {
             UserEvent::MouseOut => "mouseout",
             UserEvent::KeyDown => "keydown",
             UserEvent::KeyUp => "keyup",
+            UserEvent::Wheel => "wheel",
+            UserEvent::PointerDown => "pointerdown",
+            UserEvent::PointerMove => "pointermove",
+            UserEvent::PointerUp => "pointerup",
+            UserEvent::PointerCancel => "pointercancel",
+            UserEvent::TouchStart => "touchstart",
+            UserEvent::TouchMove => "touchmove",
+            UserEvent::TouchEnd => "touchend",
+            UserEvent::TouchCancel => "touchcancel",
+            UserEvent::ContextMenu => "contextmenu",
         }
     }
 }
 
-pub fn add_event<H>(el: &Element, user_event: &UserEvent, event_listener: H)
+type ListenerCallback = Closure<dyn FnMut(Event)>;
+
+/// An event listener added to an element. The listener is removed when this is dropped, so it
+/// must be kept for as long as the events are wanted.
+#[must_use = "the listener is removed as soon as it is dropped"]
+pub struct EventListener {
+    element: Element,
+    event_type: &'static str,
+    // `None` once the listener has been kept for the life of the page.
+    callback: Option<ListenerCallback>,
+}
+
+impl EventListener {
+    fn new(
+        element: &Element,
+        user_event: &UserEvent,
+        callback: ListenerCallback,
+    ) -> Result<EventListener, JsValue> {
+        let event_type = user_event.into();
+        let options = AddEventListenerOptions::new();
+        if let Some(passive) = user_event.passive() {
+            options.set_passive(passive);
+        }
+        element.add_event_listener_with_callback_and_add_event_listener_options(
+            event_type,
+            callback.as_ref().unchecked_ref(),
+            &options,
+        )?;
+
+        Ok(EventListener {
+            element: element.clone(),
+            event_type,
+            callback: Some(callback),
+        })
+    }
+
+    /// Removes the listener now rather than when it goes out of scope.
+    pub fn remove(self) {}
+
+    /// Keeps the listener for as long as the page is open.
+    pub fn forget(mut self) {
+        if let Some(callback) = self.callback.take() {
+            callback.forget();
+        }
+    }
+}
+
+impl Drop for EventListener {
+    fn drop(&mut self) {
+        if let Some(callback) = &self.callback {
+            // The element may already be gone, in which case there is nothing to remove.
+            let _ = self.element.remove_event_listener_with_callback(
+                self.event_type,
+                callback.as_ref().unchecked_ref(),
+            );
+        }
+    }
+}
+
+pub fn add_event<H>(
+    el: &Element,
+    user_event: &UserEvent,
+    event_listener: H,
+) -> Result<EventListener, JsValue>
 where
     H: 'static + FnMut(Event),
 {
     let cl = Closure::wrap(Box::new(event_listener) as Box<dyn FnMut(_)>);
-    el.add_event_listener_with_callback(user_event.into(), cl.as_ref().unchecked_ref())
-        .unwrap();
-    cl.forget();
+    EventListener::new(el, user_event, cl)
 }
 
-pub fn add_mouse_event<H>(el: &Element, user_event: &UserEvent, event_listener: H)
+pub fn add_mouse_event<H>(
+    el: &Element,
+    user_event: &UserEvent,
+    mut event_listener: H,
+) -> Result<EventListener, JsValue>
 where
     H: 'static + FnMut(MouseEvent),
 {
-    let cl = Closure::wrap(Box::new(event_listener) as Box<dyn FnMut(_)>);
-    el.add_event_listener_with_callback(user_event.into(), cl.as_ref().unchecked_ref())
-        .unwrap();
-    cl.forget();
+    add_event(el, user_event, move |event: Event| {
+        event_listener(event.unchecked_into::<MouseEvent>())
+    })
 }
 
-pub fn add_key_event<H>(el: &Element, user_event: &UserEvent, event_listener: H)
+pub fn add_key_event<H>(
+    el: &Element,
+    user_event: &UserEvent,
+    mut event_listener: H,
+) -> Result<EventListener, JsValue>
 where
-    H: 'static + FnMut(KeyEvent),
+    H: 'static + FnMut(KeyboardEvent),
 {
-    let cl = Closure::wrap(Box::new(event_listener) as Box<dyn FnMut(_)>);
-    el.add_event_listener_with_callback(user_event.into(), cl.as_ref().unchecked_ref())
-        .unwrap();
-    cl.forget();
+    add_event(el, user_event, move |event: Event| {
+        event_listener(event.unchecked_into::<KeyboardEvent>())
+    })
 }
 
-fn remove_event<H>(el: &Element, user_event: &UserEvent, event_listener: H)
+pub fn add_wheel_event<H>(
+    el: &Element,
+    user_event: &UserEvent,
+    mut event_listener: H,
+) -> Result<EventListener, JsValue>
 where
-    H: 'static + FnMut(Event),
+    H: 'static + FnMut(WheelEvent),
 {
-    let cl = Closure::wrap(Box::new(event_listener) as Box<dyn FnMut(_)>);
-    el.remove_event_listener_with_callback(user_event.into(), cl.as_ref().unchecked_ref())
-        .unwrap();
-    cl.forget();
+    add_event(el, user_event, move |event: Event| {
+        event_listener(event.unchecked_into::<WheelEvent>())
+    })
+}
+
+pub fn add_pointer_event<H>(
+    el: &Element,
+    user_event: &UserEvent,
+    mut event_listener: H,
+) -> Result<EventListener, JsValue>
+where
+    H: 'static + FnMut(PointerEvent),
+{
+    add_event(el, user_event, move |event: Event| {
+        event_listener(event.unchecked_into::<PointerEvent>())
+    })
+}
+
+pub fn add_touch_event<H>(
+    el: &Element,
+    user_event: &UserEvent,
+    mut event_listener: H,
+) -> Result<EventListener, JsValue>
+where
+    H: 'static + FnMut(TouchEvent),
+{
+    add_event(el, user_event, move |event: Event| {
+        event_listener(event.unchecked_into::<TouchEvent>())
+    })
+}
+
+type FrameCallback = Closure<dyn FnMut(f64)>;
+
+/// Calls `handler` on every animation frame, with the time of the frame in milliseconds. The
+/// handler runs for as long as the page is open.
+pub fn on_animation_frame<H>(mut handler: H)
+where
+    H: 'static + FnMut(f64),
+{
+    // The closure requests the next frame with itself, so it must be able to reach itself.
+    let frame: Rc<RefCell<Option<FrameCallback>>> = Rc::new(RefCell::new(None));
+    let next_frame = Rc::clone(&frame);
+
+    *frame.borrow_mut() = Some(Closure::wrap(Box::new(move |time: f64| {
+        handler(time);
+        request_animation_frame(next_frame.borrow().as_ref().unwrap());
+    }) as Box<dyn FnMut(f64)>));
+
+    request_animation_frame(frame.borrow().as_ref().unwrap());
+}
+
+fn request_animation_frame(callback: &FrameCallback) {
+    web_sys::window()
+        .expect("no global `window` exists")
+        .request_animation_frame(callback.as_ref().unchecked_ref())
+        .expect("failed to request animation frame");
 }
 
 fn get_document() -> Result<Document, RenderError> {
@@ -118,76 +281,191 @@ pub fn get_body() -> Element {
     DOCUMENT.with(|doc| -> Element { doc.borrow().body().expect("body does not exist").into() })
 }
 
-pub fn create_svg(camera: &Camera) -> Result<Element, RenderError> {
-    let svg_view = DOCUMENT.with(|doc| -> Result<Element, JsValue> {
-        doc.borrow().create_element_ns(SVG_NS, "svg")
-    })?;
+pub fn create_svg_element(name: &str) -> Result<Element, RenderError> {
+    let element = DOCUMENT
+        .with(|doc| -> Result<Element, JsValue> { doc.borrow().create_element_ns(SVG_NS, name) })?;
 
-    svg_view.set_attribute(
-        "viewBox",
-        format!(
-            "{:?} {:?} {:?} {:?}",
-            camera.min_x(),
-            camera.min_y(),
-            camera.width,
-            camera.height
-        )
-        .as_str(),
-    )?;
+    Ok(element)
+}
+
+pub fn create_html_element(name: &str) -> Result<Element, RenderError> {
+    let element =
+        DOCUMENT.with(|doc| -> Result<Element, JsValue> { doc.borrow().create_element(name) })?;
 
-    Ok(svg_view)
+    Ok(element)
 }
 
 // -----------------------------------------------
 
+/// Shape of a single cell about the origin. Every `Shape::Cell` sprite is drawn with it.
+#[derive(Debug, Clone)]
+pub struct CellGeometry {
+    pub corners: Vec<Point>,
+    pub size: f32,
+}
+
+impl CellGeometry {
+    pub fn from_layout<C, L>(layout: &L) -> CellGeometry
+    where
+        C: Cell,
+        L: Layout<C = C>,
+    {
+        let cell = layout.pixel_to_cell(layout.origin());
+        let corner = layout.cell_corner_offset(0);
+
+        CellGeometry {
+            corners: layout.polygon_corners(&cell),
+            size: corner.x.max(corner.y).abs(),
+        }
+    }
+}
+
+/// Draws layers of sprites onto a render target.
+pub trait Backend {
+    /// Moves the visible part of the world to what the camera sees.
+    fn look_at(&mut self, camera: &Camera) -> Result<(), RenderError>;
+
+    /// Brings the render target up to date with the layers, in order. Only the changes reported
+    /// by each layer since it was last rendered need to be drawn. Layers drawn before that are
+    /// missing from `layers` were removed.
+    fn render(&mut self, layers: &[Layer], cell: &CellGeometry) -> Result<(), RenderError>;
+
+    /// Defines the textures of `atlas`, replacing any defined before. Sprites drawn after this
+    /// can name one of them in their texture.
+    fn set_atlas(&mut self, atlas: &TextureAtlas) -> Result<(), RenderError>;
+
+    /// Converts a point in the browser window, such as the position of a mouse event, into
+    /// world space.
+    fn client_point(&self, client: &Point) -> Result<Point, RenderError>;
+}
+
 pub struct Viewport {
-    target_id: String,
-    svg_view: Element,
+    backend: Box<dyn Backend>,
     layers: Vec<Layer>,
+    // Geometry of the last render, kept to draw changes that happen without a layout.
+    cell: Option<CellGeometry>,
+    // Area of the world seen by the camera. Sprites outside of it are culled.
+    view: Option<Bounds>,
+    atlas: TextureAtlas,
 }
 
 impl Viewport {
+    /// Creates a viewport that renders with SVG elements inside `target_id`.
     pub fn new(target_id: &str, width: i32, height: i32) -> Result<Viewport, RenderError> {
-        // Setup the world render target. This must be done only once.
-        let target = get_target(target_id)?;
-        let render_camera = Camera::new(width, height);
-        let svg_view = create_svg(&render_camera)?;
-        svg_view.set_attribute("id", "svg_view")?;
-        target.append_child(&svg_view)?;
-        Ok(Viewport {
-            target_id: target_id.to_owned(),
-            svg_view: svg_view,
+        Ok(Viewport::with_backend(Box::new(SvgBackend::new(
+            target_id, width, height,
+        )?)))
+    }
+
+    pub fn with_backend(backend: Box<dyn Backend>) -> Viewport {
+        Viewport {
+            backend: backend,
             layers: Vec::new(),
-        })
+            cell: None,
+            view: None,
+            atlas: TextureAtlas::new(),
+        }
     }
 
-    pub fn look_at(&mut self, camera: &Camera) -> Result<(), RenderError> {
-        self.svg_view.set_attribute(
-            "viewBox",
-            format!(
-                "{:?} {:?} {:?} {:?}",
-                camera.min_x(),
-                camera.min_y(),
-                camera.width,
-                camera.height,
-            )
-            .as_str(),
-        )?;
+    /// Shares the textures of `atlas` with the sprites of this viewport. This must be done
+    /// before rendering the sprites that name them.
+    pub fn set_atlas(&mut self, atlas: TextureAtlas) -> Result<(), RenderError> {
+        self.backend.set_atlas(&atlas)?;
+        self.atlas = atlas;
 
         Ok(())
     }
 
+    pub fn atlas(&self) -> &TextureAtlas {
+        &self.atlas
+    }
+
+    /// Shows what `camera` sees. Sprites that come into view are drawn and the ones that leave
+    /// it are removed.
+    pub fn look_at(&mut self, camera: &Camera) -> Result<(), RenderError> {
+        self.backend.look_at(camera)?;
+
+        let view = camera.view_bounds().expand(CULL_MARGIN);
+        if self.view.as_ref() != Some(&view) {
+            for layer in &mut self.layers {
+                layer.set_view(Some(view.clone()));
+            }
+            self.view = Some(view);
+        }
+
+        self.flush()
+    }
+
+    /// Draws everything that changed since the last render. Rendering an unchanged viewport
+    /// does nothing.
+    pub fn render<C, L>(&mut self, layout: &L) -> Result<(), RenderError>
+    where
+        C: Cell,
+        L: Layout<C = C>,
+    {
+        self.cell = Some(CellGeometry::from_layout(layout));
+        self.flush()
+    }
+
+    pub fn render_layer<C, L>(&mut self, layer_name: &str, layout: &L) -> Result<(), RenderError>
+    where
+        C: Cell,
+        L: Layout<C = C>,
+    {
+        self.layer_index(layer_name)?;
+
+        // Only changed layers are drawn, so rendering everything costs no more.
+        self.render(layout)
+    }
+
+    pub fn clear(&mut self) -> Result<(), RenderError> {
+        for layer in &mut self.layers {
+            layer.clear();
+        }
+
+        self.flush()
+    }
+
     pub fn clear_layer(&mut self, layer_name: &str) -> Result<(), RenderError> {
-        match self.layer_mut(layer_name) {
-            Some(layer) => {
-                layer.clear()?;
+        if let Some(layer) = self.layer_mut(layer_name) {
+            // Remove all sprite objects.
+            layer.clear();
+        }
+
+        self.flush()
+    }
+
+    // Draws any changes to the layers with the geometry of the last render. Nothing has been
+    // drawn before the first render, so there is nothing to update until then.
+    fn flush(&mut self) -> Result<(), RenderError> {
+        if let Some(cell) = &self.cell {
+            for layer in &mut self.layers {
+                layer.update_moved();
             }
-            None => {}
-        };
+
+            self.backend.render(&self.layers, cell)?;
+
+            for layer in &mut self.layers {
+                layer.mark_clean();
+            }
+        }
 
         Ok(())
     }
 
+    fn layer_index(&self, layer_name: &str) -> Result<usize, RenderError> {
+        match self
+            .layers
+            .iter()
+            .position(|layer| layer.name == layer_name)
+        {
+            Some(index) => Ok(index),
+            None => Err(RenderError::new(
+                format!("layer {:?} does not exist", layer_name).as_str(),
+            )),
+        }
+    }
+
     pub fn layer(&self, layer_name: &str) -> Option<&Layer> {
         for layer in &self.layers {
             if layer.name == layer_name {
@@ -206,34 +484,128 @@ impl Viewport {
         None
     }
 
-    pub fn insert_layer(&mut self, order: usize, layer: Layer) {
+    pub fn insert_layer(&mut self, order: usize, mut layer: Layer) {
+        layer.set_view(self.view.clone());
         self.layers.insert(order, layer);
     }
 
+    /// Moves the layer `layer_name` to `order`, or to the top if `order` is past the last layer.
+    pub fn move_layer(&mut self, layer_name: &str, order: usize) -> Result<(), RenderError> {
+        let index = self.layer_index(layer_name)?;
+        let layer = self.layers.remove(index);
+        let order = order.min(self.layers.len());
+        self.layers.insert(order, layer);
+
+        self.flush()
+    }
+
     pub fn remove_layer(&mut self, layer_name: &str) {
         self.layers.retain(|layer| layer.name != layer_name);
+        self.flush().expect("failed to clear removed layer");
+    }
+
+    /// Writes every layer, as seen by `camera`, out as a standalone SVG document. This does not
+    /// depend on the backend or on anything having been rendered.
+    pub fn export_svg<C, L>(&self, camera: &Camera, layout: &L) -> String
+    where
+        C: Cell,
+        L: Layout<C = C>,
+    {
+        export_svg(
+            camera,
+            &self.layers,
+            &CellGeometry::from_layout(layout),
+            &self.atlas,
+        )
     }
 
     pub fn event_point(&self, event: &MouseEvent) -> Result<Point, RenderError> {
-        // Get point in global SVG space
-        let svg_matrix = self
-            .svg_view
-            .clone()
-            .dyn_into::<SvgsvgElement>()?
-            .get_screen_ctm()
-            .expect("failed to get screen ctm")
-            .inverse()
-            .expect("failed to get inverse");
-        let svg_point = self
-            .svg_view
-            .clone()
-            .dyn_into::<SvgsvgElement>()?
-            .create_svg_point();
-        svg_point.set_x(event.client_x() as f32);
-        svg_point.set_y(event.client_y() as f32);
-        let svg_point = svg_point.matrix_transform(&svg_matrix);
-
-        Ok(Point::new(svg_point.x(), svg_point.y()))
+        self.client_point(&Point::new(
+            event.client_x() as f32,
+            event.client_y() as f32,
+        ))
+    }
+
+    /// Converts a point in the browser window into world space.
+    pub fn client_point(&self, client: &Point) -> Result<Point, RenderError> {
+        self.backend.client_point(client)
+    }
+
+    /// Returns the top sprite covering the world point `point`, searching visible and
+    /// interactive layers from the top. Nothing is found before the first render.
+    pub fn hit_test(&self, point: &Point) -> Option<Hit> {
+        let cell = self.cell.as_ref()?;
+
+        self.layers
+            .iter()
+            .rev()
+            .filter(|layer| layer.is_visible() && layer.is_interactive())
+            .find_map(|layer| {
+                layer.hit_test(point, cell).map(|(coord, sprite)| Hit {
+                    layer: layer.name.clone(),
+                    coord,
+                    sprite_id: sprite.id().to_owned(),
+                })
+            })
+    }
+}
+
+/// A sprite found under a point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub layer: String,
+    pub coord: CellCoord,
+    pub sprite_id: String,
+}
+
+impl Hit {
+    /// Indicates if both hits are on the same place of the same layer, even if the sprite there
+    /// was replaced.
+    pub fn is_same_entity(&self, other: &Hit) -> bool {
+        self.layer == other.layer && self.coord == other.coord
+    }
+}
+
+/// The smallest zoom. The camera sees four times its size.
+pub const MIN_ZOOM: f32 = 0.25;
+/// The largest zoom. The camera sees a quarter of its size.
+pub const MAX_ZOOM: f32 = 4.0;
+/// Factor applied by a single step of zooming in or out.
+pub const ZOOM_STEP: f32 = 1.25;
+/// Distance around the camera's view in which sprites are still drawn, so they are already
+/// there when panning brings them into view.
+pub const CULL_MARGIN: f32 = 64.0;
+
+/// Area of the world the camera may show.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub fn new(min: Point, max: Point) -> Bounds {
+        Bounds { min, max }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max.y - self.min.y
+    }
+
+    pub fn center(&self) -> Point {
+        &(&self.min + &self.max) / 2.0
+    }
+
+    /// Returns the bounds grown by `margin` on every side.
+    pub fn expand(&self, margin: f32) -> Bounds {
+        Bounds::new(
+            Point::new(self.min.x - margin, self.min.y - margin),
+            Point::new(self.max.x + margin, self.max.y + margin),
+        )
     }
 }
 
@@ -242,6 +614,8 @@ pub struct Camera {
     pub width: i32,
     pub height: i32,
     pub position: Point,
+    zoom: f32,
+    bounds: Option<Bounds>,
 }
 
 impl Camera {
@@ -250,18 +624,107 @@ impl Camera {
             width: width,
             height: height,
             position: Point::origin(),
+            zoom: 1.0,
+            bounds: None,
         }
     }
     pub fn look_at(&mut self, position: &Point) {
         self.position = position.clone();
+        self.clamp();
     }
 
-    fn min_x(&self) -> i32 {
-        self.position.x as i32 - (self.width / 2)
+    /// Moves the camera by `delta` in world space.
+    pub fn pan(&mut self, delta: &Point) {
+        self.look_at(&(&self.position + delta));
     }
 
-    fn min_y(&self) -> i32 {
-        self.position.y as i32 - (self.height / 2)
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Scales the view by `factor` about `point`, which stays at the same place on screen.
+    pub fn zoom_at(&mut self, factor: f32, point: &Point) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let offset = &(&self.position - point) * (self.zoom / zoom);
+
+        self.zoom = zoom;
+        self.look_at(&(point + &offset));
+    }
+
+    /// Shows all of `bounds`, centered. Unlike zooming, this is not limited to `MIN_ZOOM` and
+    /// `MAX_ZOOM`, so any area can be fitted.
+    pub fn fit(&mut self, bounds: &Bounds) {
+        let zoom_x = self.width as f32 / bounds.width();
+        let zoom_y = self.height as f32 / bounds.height();
+
+        self.zoom = zoom_x.min(zoom_y);
+        self.look_at(&bounds.center());
+    }
+
+    pub fn bounds(&self) -> Option<&Bounds> {
+        self.bounds.as_ref()
+    }
+
+    /// Keeps the view inside `bounds`. A view larger than the bounds is centered on them.
+    pub fn set_bounds(&mut self, bounds: Option<Bounds>) {
+        self.bounds = bounds;
+        self.clamp();
+    }
+
+    /// Width of the world seen by the camera.
+    pub fn view_width(&self) -> f32 {
+        self.width as f32 / self.zoom
+    }
+
+    /// Height of the world seen by the camera.
+    pub fn view_height(&self) -> f32 {
+        self.height as f32 / self.zoom
+    }
+
+    /// Returns the area of the world seen by the camera.
+    pub fn view_bounds(&self) -> Bounds {
+        Bounds::new(
+            Point::new(self.min_x(), self.min_y()),
+            Point::new(
+                self.min_x() + self.view_width(),
+                self.min_y() + self.view_height(),
+            ),
+        )
+    }
+
+    pub fn min_x(&self) -> f32 {
+        self.position.x - self.view_width() / 2.0
+    }
+
+    pub fn min_y(&self) -> f32 {
+        self.position.y - self.view_height() / 2.0
+    }
+
+    fn clamp(&mut self) {
+        if let Some(bounds) = &self.bounds {
+            let clamp_axis = |position: f32, min: f32, max: f32, view: f32| {
+                if max - min <= view {
+                    (min + max) / 2.0
+                } else {
+                    position.clamp(min + view / 2.0, max - view / 2.0)
+                }
+            };
+
+            self.position = Point::new(
+                clamp_axis(
+                    self.position.x,
+                    bounds.min.x,
+                    bounds.max.x,
+                    self.view_width(),
+                ),
+                clamp_axis(
+                    self.position.y,
+                    bounds.min.y,
+                    bounds.max.y,
+                    self.view_height(),
+                ),
+            );
+        }
     }
 }
 
@@ -342,7 +805,15 @@ impl TextureBorder {
         }
     }
 
-    fn style_str(&self) -> String {
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn color(&self) -> &str {
+        &self.color
+    }
+
+    pub fn style_str(&self) -> String {
         // Note: Do not use {:?} or else it prints quotes around the color string.
         format!(
             "stroke:{};stroke-width:{:?};",
@@ -357,6 +828,9 @@ pub struct Texture {
     pub image: Option<String>,
     pub background_color: Option<String>,
     pub border: Option<TextureBorder>,
+    /// Name of a texture in the viewport's atlas. A symbol is drawn instead of the image, and a
+    /// pattern or gradient fills the shape instead of the background color.
+    pub name: Option<String>,
 }
 
 impl Texture {
@@ -365,10 +839,18 @@ impl Texture {
             image: None,
             background_color: None,
             border: None,
+            name: None,
         }
     }
 
-    fn style_str(&self) -> String {
+    /// Creates a texture that uses the texture `name` of the viewport's atlas.
+    pub fn named(name: &str) -> Texture {
+        let mut texture = Texture::new();
+        texture.name = Some(name.to_owned());
+        texture
+    }
+
+    pub fn style_str(&self) -> String {
         let mut style = "".to_owned();
         if let Some(bg_color) = &self.background_color {
             style = style + "fill:" + bg_color.as_str() + ";";
@@ -381,15 +863,140 @@ impl Texture {
         style
     }
 }
+
+/// How the line of a polyline or path is drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke {
+    pub color: String,
+    pub width: i32,
+    /// Lengths of the dashes and gaps of the line, in turn. The line is solid if empty.
+    pub dash: Vec<i32>,
+    /// Indicates if an arrowhead is drawn at the first point.
+    pub start_arrow: bool,
+    /// Indicates if an arrowhead is drawn at the last point.
+    pub end_arrow: bool,
+}
+
+impl Stroke {
+    pub fn new(width: i32, color: &str) -> Stroke {
+        Stroke {
+            color: color.to_owned(),
+            width,
+            dash: Vec::new(),
+            start_arrow: false,
+            end_arrow: false,
+        }
+    }
+
+    pub fn style_str(&self) -> String {
+        let mut style = format!(
+            "fill:none;stroke:{};stroke-width:{};stroke-linecap:round;stroke-linejoin:round;",
+            self.color, self.width
+        );
+        if !self.dash.is_empty() {
+            let dash: Vec<String> = self.dash.iter().map(|length| length.to_string()).collect();
+            style.push_str(&format!("stroke-dasharray:{};", dash.join(",")));
+        }
+        style
+    }
+
+    /// Returns the corners of the arrowheads at the ends of a line through `points`. Each one
+    /// is a triangle whose tip is on the end of the line.
+    pub fn arrowheads(&self, points: &[Point]) -> Vec<[Point; 3]> {
+        let mut arrowheads = Vec::new();
+        if points.len() < 2 {
+            return arrowheads;
+        }
+
+        let size = (self.width * ARROW_SIZE) as f32;
+        let mut add = |tip: &Point, from: &Point| {
+            let direction = tip - from;
+            let length = (direction.x * direction.x + direction.y * direction.y).sqrt();
+            if length == 0.0 {
+                return;
+            }
+
+            let direction = &direction / length;
+            let base = tip - &(&direction * size);
+            let side = &Point::new(-direction.y, direction.x) * (size / 2.0);
+            arrowheads.push([tip.clone(), &base + &side, &base - &side]);
+        };
+
+        if self.start_arrow {
+            add(&points[0], &points[1]);
+        }
+        if self.end_arrow {
+            add(&points[points.len() - 1], &points[points.len() - 2]);
+        }
+
+        arrowheads
+    }
+}
+
+// Length of an arrowhead as a multiple of the width of its line.
+const ARROW_SIZE: i32 = 3;
+
+/// Which part of a text is placed at the sprite's position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+impl TextAnchor {
+    pub fn svg_name(&self) -> &str {
+        match self {
+            TextAnchor::Start => "start",
+            TextAnchor::Middle => "middle",
+            TextAnchor::End => "end",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Shape {
     Cell,
-    Rectangle { width: i32, height: i32 },
-    Circle { radius: i32 },
+    Rectangle {
+        width: i32,
+        height: i32,
+    },
+    Circle {
+        radius: i32,
+    },
+    /// A line of text, vertically centered on the sprite's position. It is filled with the
+    /// texture's background color, or black without one.
+    Text {
+        text: String,
+        font: String,
+        size: i32,
+        anchor: TextAnchor,
+    },
+    /// Straight lines joining points about the sprite's position.
+    Polyline {
+        points: Vec<Point>,
+        stroke: Stroke,
+    },
+    /// A smooth line through points about the sprite's position. It starts and ends on the
+    /// first and last points, and rounds each corner between them.
+    Path {
+        points: Vec<Point>,
+        stroke: Stroke,
+    },
 }
 
 impl Shape {
-    fn svg_name(&self) -> &str {
+    /// Creates a text shape centered on the sprite's position.
+    pub fn text(text: &str, font: &str, size: i32) -> Shape {
+        Shape::Text {
+            text: text.to_owned(),
+            font: font.to_owned(),
+            size,
+            anchor: TextAnchor::Middle,
+        }
+    }
+
+    pub fn svg_name(&self) -> &str {
         match self {
             Shape::Cell => "polygon",
             Shape::Rectangle {
@@ -397,27 +1004,124 @@ impl Shape {
                 height: _,
             } => "rect",
             Shape::Circle { radius: _ } => "circle",
+            Shape::Text { .. } => "text",
+            Shape::Polyline { .. } => "polyline",
+            Shape::Path { .. } => "path",
+        }
+    }
+
+    /// Indicates if `point`, about the origin, is inside the shape. Lines contain the points
+    /// under their stroke, and paths are treated as straight lines between their points.
+    pub fn contains(&self, point: &Point, cell: &CellGeometry) -> bool {
+        match self {
+            Shape::Cell => polygon_contains(&cell.corners, point),
+            Shape::Rectangle { width, height } => {
+                point.x.abs() <= *width as f32 / 2.0 && point.y.abs() <= *height as f32 / 2.0
+            }
+            Shape::Circle { radius } => {
+                point.x * point.x + point.y * point.y <= (*radius * *radius) as f32
+            }
+            Shape::Text {
+                text, size, anchor, ..
+            } => {
+                // Text is measured by the browser, so guess its width from its length.
+                let width = text.chars().count() as f32 * *size as f32 * TEXT_WIDTH_RATIO;
+                let min_x = match anchor {
+                    TextAnchor::Start => 0.0,
+                    TextAnchor::Middle => -width / 2.0,
+                    TextAnchor::End => -width,
+                };
+
+                point.x >= min_x && point.x <= min_x + width && point.y.abs() <= *size as f32 / 2.0
+            }
+            Shape::Polyline { points, stroke } | Shape::Path { points, stroke } => {
+                points.windows(2).any(|line| {
+                    segment_distance(point, &line[0], &line[1]) <= stroke.width as f32 / 2.0
+                })
+            }
         }
     }
 }
 
+// Average width of a character as a fraction of the font size.
+const TEXT_WIDTH_RATIO: f32 = 0.6;
+
+// Indicates if `point` is inside the polygon with `corners`, by counting the edges crossed by a
+// line from the point to the right.
+fn polygon_contains(corners: &[Point], point: &Point) -> bool {
+    let mut inside = false;
+
+    for (i, corner) in corners.iter().enumerate() {
+        let previous = &corners[(i + corners.len() - 1) % corners.len()];
+        if (corner.y > point.y) != (previous.y > point.y) {
+            let crossing_x =
+                (previous.x - corner.x) * (point.y - corner.y) / (previous.y - corner.y) + corner.x;
+            if point.x < crossing_x {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
+
+// Returns the distance from `point` to the closest point on the line from `from` to `to`.
+fn segment_distance(point: &Point, from: &Point, to: &Point) -> f32 {
+    let line = to - from;
+    let length_squared = line.x * line.x + line.y * line.y;
+
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (((point.x - from.x) * line.x + (point.y - from.y) * line.y) / length_squared)
+            .clamp(0.0, 1.0)
+    };
+
+    let closest = from + &(&line * t);
+    let (dx, dy) = (point.x - closest.x, point.y - closest.y);
+
+    (dx * dx + dy * dy).sqrt()
+}
+
 #[derive(Debug, Clone)]
 pub struct Sprite {
     id: String,
     shape: Shape,
     position: Point,
+    // Clockwise rotation in degrees about the position.
+    rotation: f32,
+    scale: f32,
+    opacity: f32,
+    // Sprites with a higher z-index are drawn over the ones with a lower one.
+    z_index: i32,
+    // Order the sprite was added to its layer in. Breaks ties between equal z-indexes.
+    sequence: u64,
     texture: Texture,
+    // Text shown while the mouse is over the sprite.
+    tooltip: Option<String>,
     visible: bool,
+    dirty: bool,
 }
 
+/// Position of a sprite in the drawing order of its layer. Sprites are drawn from the lowest to
+/// the highest.
+pub type DrawOrder = (i32, u64);
+
 impl Sprite {
     pub fn new(id: &str, shape: &Shape, position: &Point, texture: &Texture) -> Sprite {
         Sprite {
             id: id.to_owned(),
             shape: shape.clone(),
             position: position.clone(),
+            rotation: 0.0,
+            scale: 1.0,
+            opacity: 1.0,
+            z_index: 0,
+            sequence: 0,
             texture: texture.clone(),
+            tooltip: None,
             visible: true,
+            dirty: true,
         }
     }
 
@@ -425,266 +1129,427 @@ impl Sprite {
         &self.id
     }
 
-    pub fn visible(&mut self, visible: bool) {
-        self.visible = visible;
+    pub fn shape(&self) -> &Shape {
+        &self.shape
     }
 
-    pub fn on<H>(id: &str, event: UserEvent, handler: H) -> Result<(), JsValue>
-    where
-        H: 'static + FnMut(Event),
-    {
-        let sprite_element = get_target(id)?;
-        add_event(&sprite_element, &event, handler);
+    pub fn position(&self) -> &Point {
+        &self.position
+    }
 
-        Ok(())
+    /// Returns the clockwise rotation in degrees.
+    pub fn rotation(&self) -> f32 {
+        self.rotation
     }
 
-    pub fn remove_listener<H>(id: &str, event: UserEvent, handler: H) -> Result<(), JsValue>
-    where
-        H: 'static + FnMut(Event),
-    {
-        let sprite_element = get_target(id)?;
-        remove_event(&sprite_element, &event, handler);
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
 
-        Ok(())
+    pub fn opacity(&self) -> f32 {
+        self.opacity
     }
-}
 
-pub struct Layer {
-    pub name: String,
-    pub sprites: HashMap<CellCoord, Sprite>,
-}
+    pub fn z_index(&self) -> i32 {
+        self.z_index
+    }
 
-impl Layer {
-    pub fn new(name: &str) -> Layer {
-        Layer {
-            name: name.to_owned(),
-            sprites: HashMap::new(),
+    /// Indicates if the sprite covers the world point `point`, taking its position, rotation and
+    /// scale into account.
+    pub fn contains(&self, point: &Point, cell: &CellGeometry) -> bool {
+        if self.scale == 0.0 {
+            return false;
         }
-    }
 
-    pub fn add_sprite(&mut self, coord: CellCoord, sprite: Sprite) {
-        self.sprites.insert(coord, sprite);
-    }
+        // Undo the sprite's transform to find the point about the origin of its shape.
+        let offset = point - &self.position;
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let local = Point::new(
+            (offset.x * cos + offset.y * sin) / self.scale,
+            (offset.y * cos - offset.x * sin) / self.scale,
+        );
 
-    pub fn remove_sprite(&mut self, sprite_id: String) {
-        // FIXME: Not performant. Must iterate all sprites.
-        self.sprites.retain(|_, sprite| sprite.id != sprite_id);
+        self.shape.contains(&local, cell)
     }
-}
 
-// ------- Renderable --------
+    /// Returns where the sprite is drawn in its layer. Sprites with the same z-index are drawn
+    /// in the order they were added to the layer.
+    pub fn draw_order(&self) -> DrawOrder {
+        (self.z_index, self.sequence)
+    }
 
-pub trait Renderable {
-    fn clear(&mut self) -> Result<(), RenderError>;
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
 
-    fn render<C, L>(&self, layout: &L) -> Result<Element, RenderError>
-    where
-        C: Cell,
-        L: Layout<C = C>;
-}
+    pub fn tooltip(&self) -> Option<&str> {
+        self.tooltip.as_deref()
+    }
 
-impl Renderable for Viewport {
-    fn clear(&mut self) -> Result<(), RenderError> {
-        for layer in &mut self.layers {
-            layer.clear()?;
-        }
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
 
-        Ok(())
+    /// Indicates if the sprite changed since it was last rendered.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
     }
 
-    fn render<C, L>(&self, layout: &L) -> Result<Element, RenderError>
-    where
-        C: Cell,
-        L: Layout<C = C>,
-    {
-        debug("rendering viewport".to_owned());
+    pub fn set_shape(&mut self, shape: &Shape) {
+        self.shape = shape.clone();
+        self.dirty = true;
+    }
 
-        for layer in &self.layers {
-            let layer_view = layer.render(layout)?;
-            debug(format!("appending layer view: {}", layer.name));
-            self.svg_view
-                .append_child(&layer_view)
-                .expect("failed to append child");
-        }
+    pub fn set_position(&mut self, position: &Point) {
+        self.position = position.clone();
+        self.dirty = true;
+    }
 
-        debug("viewport rendered".to_owned());
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+        self.dirty = true;
+    }
 
-        Ok(self.svg_view.clone())
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+        self.dirty = true;
     }
-}
 
-impl Renderable for Layer {
-    fn clear(&mut self) -> Result<(), RenderError> {
-        let layer_view = match get_target(&self.name) {
-            Ok(e) => e,
-            Err(_) => {
-                error(format!("failed to clear layer: {}", self.name));
-                return Ok(()); // Ignore errors on clear. The elements might not exist.
-            }
-        };
+    /// Sets how opaque the sprite is, from 0.0 (invisible) to 1.0.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+        self.dirty = true;
+    }
 
-        for (_, sprite) in &mut self.sprites {
-            sprite.clear()?;
-        }
+    /// Sets the text shown while the mouse is over the sprite. `None` removes it.
+    pub fn set_tooltip(&mut self, tooltip: Option<&str>) {
+        self.tooltip = tooltip.map(|tooltip| tooltip.to_owned());
+        self.dirty = true;
+    }
 
-        // Remove all sprite objects.
-        self.sprites.clear();
+    pub fn set_z_index(&mut self, z_index: i32) {
+        self.z_index = z_index;
+        self.dirty = true;
+    }
 
-        // Clear everything in this layer.
-        layer_view.set_inner_html("");
+    pub fn set_texture(&mut self, texture: &Texture) {
+        self.texture = texture.clone();
+        self.dirty = true;
+    }
 
-        Ok(())
+    /// Shows or hides the sprite. Hidden sprites are not drawn, as if they were culled.
+    pub fn visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.dirty = true;
     }
 
-    fn render<C, L>(&self, layout: &L) -> Result<Element, RenderError>
+    /// Calls `handler` with the events on the element of the sprite `id` until the returned
+    /// listener is dropped.
+    pub fn on<H>(id: &str, event: UserEvent, handler: H) -> Result<EventListener, JsValue>
     where
-        C: Cell,
-        L: Layout<C = C>,
+        H: 'static + FnMut(Event),
     {
-        debug(format!("rendering layer: {}", self.name));
+        let sprite_element = get_target(id)?;
+        add_event(&sprite_element, &event, handler)
+    }
+}
 
-        let layer_view = match get_target(&self.name) {
-            Ok(e) => e,
-            Err(_) => DOCUMENT.with(|doc| -> Result<Element, JsValue> {
-                let layer_view = doc.borrow().create_element_ns(SVG_NS, "g")?;
+pub struct Layer {
+    pub name: String,
+    sprites: HashMap<CellCoord, Sprite>,
+    index: SpatialIndex,
+    // Area the layer is seen through. Sprites outside of it are culled.
+    view: Option<Bounds>,
+    // Visible sprites inside the view. These are the sprites drawn once the layer is rendered.
+    shown: HashSet<CellCoord>,
+    // Sprites shown since the layer was last rendered.
+    added: HashSet<CellCoord>,
+    // Sprites borrowed mutably since the layer was last rendered. Only the dirty ones are drawn.
+    touched: HashSet<CellCoord>,
+    // Ids of sprites hidden or removed since the layer was last rendered.
+    removed: Vec<String>,
+    // Sequence given to the next sprite added.
+    next_sequence: u64,
+    visible: bool,
+    opacity: f32,
+    // Indicates that the visibility or opacity changed since the layer was last rendered.
+    restyled: bool,
+    interactive: bool,
+}
 
-                layer_view.set_attribute("id", &self.name)?;
+impl Layer {
+    pub fn new(name: &str) -> Layer {
+        Layer {
+            name: name.to_owned(),
+            sprites: HashMap::new(),
+            index: SpatialIndex::new(),
+            view: None,
+            shown: HashSet::new(),
+            added: HashSet::new(),
+            touched: HashSet::new(),
+            removed: Vec::new(),
+            next_sequence: 0,
+            visible: true,
+            opacity: 1.0,
+            restyled: false,
+            interactive: true,
+        }
+    }
 
-                Ok(layer_view)
-            })?,
-        };
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
 
-        for (_, sprite) in &self.sprites {
-            let sprite_view = sprite.render(layout)?;
-            // Add the sprite to the layer.
-            layer_view.append_child(&sprite_view)?;
+    /// Shows or hides every sprite of the layer. A hidden layer keeps its sprites up to date, so
+    /// showing it again is cheap.
+    pub fn set_visible(&mut self, visible: bool) {
+        if self.visible != visible {
+            self.visible = visible;
+            self.restyled = true;
         }
+    }
 
-        debug(format!("layer rendered: {}", self.name));
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
 
-        Ok(layer_view)
+    pub fn is_interactive(&self) -> bool {
+        self.interactive
     }
-}
 
-impl Renderable for Sprite {
-    fn clear(&mut self) -> Result<(), RenderError> {
-        match get_target(self.id()) {
-            Ok(sprite_view) => {
-                // Remove the sprite from the DOM.
-                sprite_view.remove();
-                Ok(())
-            }
-            Err(_) => Ok(()), // Ignore errors on clear. The elements might not exist.
+    /// Sets if the sprites of the layer are found by hit testing. Overlays such as labels can
+    /// opt out so the sprites under them are found instead.
+    pub fn set_interactive(&mut self, interactive: bool) {
+        self.interactive = interactive;
+    }
+
+    /// Sets how opaque the whole layer is, from 0.0 (invisible) to 1.0. It applies on top of
+    /// the opacity of each sprite.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        if self.opacity != opacity {
+            self.opacity = opacity;
+            self.restyled = true;
         }
     }
 
-    fn render<C, L>(&self, layout: &L) -> Result<Element, RenderError>
-    where
-        C: Cell,
-        L: Layout<C = C>,
-    {
-        debug(format!("rendering sprite: {}", self.id()));
+    /// Adds a sprite on top of the sprites with the same z-index. A sprite replacing another at
+    /// the same `coord` keeps its place in the drawing order.
+    pub fn add_sprite(&mut self, coord: CellCoord, mut sprite: Sprite) {
+        sprite.sequence = match self.sprites.get(&coord) {
+            Some(old) => old.sequence,
+            None => {
+                self.next_sequence += 1;
+                self.next_sequence
+            }
+        };
 
-        // Group all of a sprites data together.
-        let sprite_view = DOCUMENT.with(|doc| -> Result<Element, JsValue> {
-            doc.borrow().create_element_ns(SVG_NS, "g")
-        })?;
+        self.index.insert(coord, &sprite.position);
+        let shown = self.is_shown(&sprite);
 
-        sprite_view.set_attribute("id", self.id())?;
+        if let Some(old) = self.sprites.insert(coord, sprite) {
+            // A sprite with the same id that stays shown is replaced when it is drawn.
+            if self.shown.remove(&coord) && (!shown || old.id != self.sprites[&coord].id) {
+                self.removed.push(old.id);
+            }
+        }
 
-        // A sprite is defined as a polygon of any shape.
-        let width;
-        let height;
+        if shown {
+            self.shown.insert(coord);
+            self.added.insert(coord);
+        } else {
+            self.added.remove(&coord);
+        }
+    }
 
-        let svg_element = DOCUMENT.with(|doc| -> Result<Element, JsValue> {
-            doc.borrow()
-                .create_element_ns(SVG_NS, self.shape.svg_name())
-        })?;
+    pub fn remove_sprite(&mut self, sprite_id: String) {
+        // FIXME: Not performant. Must iterate all sprites.
+        let coord = self
+            .sprites
+            .iter()
+            .find(|(_, sprite)| sprite.id == sprite_id)
+            .map(|(coord, _)| *coord);
+
+        if let Some(coord) = coord {
+            self.sprites.remove(&coord);
+            self.index.remove(&coord);
+            self.added.remove(&coord);
+            self.touched.remove(&coord);
+            if self.shown.remove(&coord) {
+                self.removed.push(sprite_id);
+            }
+        }
+    }
 
-        let sprite_polygon = match self.shape {
-            Shape::Cell => {
-                let cell = layout.pixel_to_cell(&self.position);
+    pub fn sprite(&self, coord: &CellCoord) -> Option<&Sprite> {
+        self.sprites.get(coord)
+    }
 
-                let mut corners_string: String = "".to_owned();
-                for corner in layout.polygon_corners(&cell) {
-                    corners_string.push_str(String::from(&corner).as_str());
-                    corners_string.push_str(" ");
-                }
+    pub fn sprite_mut(&mut self, coord: &CellCoord) -> Option<&mut Sprite> {
+        let sprite = self.sprites.get_mut(coord)?;
+        self.touched.insert(*coord);
 
-                svg_element.set_attribute("points", corners_string.as_str())?;
+        Some(sprite)
+    }
 
-                let cell_size: f32 = layout
-                    .cell_corner_offset(0)
-                    .x
-                    .max(layout.cell_corner_offset(0).y)
-                    .abs();
+    /// Returns every sprite in the layer, including culled ones.
+    pub fn sprites(&self) -> impl Iterator<Item = (&CellCoord, &Sprite)> {
+        self.sprites.iter()
+    }
 
-                width = cell_size;
-                height = cell_size;
+    /// Returns the visible sprites inside the view in the order they are drawn.
+    pub fn shown_sprites(&self) -> Vec<&Sprite> {
+        let mut sprites: Vec<&Sprite> = self
+            .shown
+            .iter()
+            .filter_map(|coord| self.sprites.get(coord))
+            .collect();
+        sprites.sort_by_key(|sprite| sprite.draw_order());
 
-                svg_element
-            }
-            Shape::Rectangle {
-                width: rect_width,
-                height: rect_height,
-            } => {
-                svg_element.set_attribute("width", rect_width.to_string().as_str())?;
-                svg_element.set_attribute("height", rect_height.to_string().as_str())?;
-                svg_element.set_attribute("x", (-rect_width as f32 / 2.0).to_string().as_str())?;
-                svg_element.set_attribute("y", (-rect_height as f32 / 2.0).to_string().as_str())?;
+        sprites
+    }
 
-                width = rect_width as f32;
-                height = rect_height as f32;
+    /// Returns every sprite in the layer, including culled ones, in the order they are drawn.
+    pub fn ordered_sprites(&self) -> Vec<&Sprite> {
+        let mut sprites: Vec<&Sprite> = self.sprites.values().collect();
+        sprites.sort_by_key(|sprite| sprite.draw_order());
 
-                svg_element
-            }
-            Shape::Circle { radius } => {
-                svg_element.set_attribute("cx", "0")?;
-                svg_element.set_attribute("cy", "0")?;
-                svg_element.set_attribute("r", radius.to_string().as_str())?;
+        sprites
+    }
+
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
 
-                width = radius as f32;
-                height = radius as f32;
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
 
-                svg_element
+    /// Removes every sprite from the layer.
+    pub fn clear(&mut self) {
+        for coord in self.shown.drain() {
+            if let Some(sprite) = self.sprites.get(&coord) {
+                self.removed.push(sprite.id.clone());
             }
+        }
+        self.sprites.clear();
+        self.index.clear();
+        self.next_sequence = 0;
+        self.added.clear();
+        self.touched.clear();
+    }
+
+    /// Culls the sprites outside of `view`. Sprites that enter the view are drawn by the next
+    /// render and the ones that leave it are removed. `None` shows every visible sprite.
+    pub fn set_view(&mut self, view: Option<Bounds>) {
+        let shown: HashSet<CellCoord> = match &view {
+            Some(bounds) => self.index.query(bounds).copied().collect(),
+            None => self.sprites.keys().copied().collect(),
         };
+        let shown = shown
+            .into_iter()
+            .filter(|coord| self.sprites[coord].visible)
+            .collect();
+
+        for coord in self.shown.difference(&shown) {
+            self.added.remove(coord);
+            self.removed.push(self.sprites[coord].id.clone());
+        }
+        for coord in shown.difference(&self.shown) {
+            self.added.insert(*coord);
+        }
 
-        sprite_polygon.set_attribute("style", self.texture.style_str().as_str())?;
+        self.shown = shown;
+        self.view = view;
+    }
 
-        // Add the polygon shape to the sprite group.
-        sprite_view.append_child(&sprite_polygon)?;
+    /// Indicates if the layer changed since it was last rendered.
+    pub fn is_dirty(&self) -> bool {
+        self.restyled
+            || !self.added.is_empty()
+            || !self.removed.is_empty()
+            || self.changed_sprites().next().is_some()
+    }
 
-        // Set any texture for the sprite as an <image> child of the sprite group.
-        if let Some(image) = &self.texture.image {
-            let sprite_image = DOCUMENT.with(|doc| -> Result<Element, JsValue> {
-                doc.borrow().create_element_ns(SVG_NS, "image")
-            })?;
+    /// Returns the sprites in the view that were added or changed since the layer was last
+    /// rendered.
+    pub fn changed_sprites(&self) -> impl Iterator<Item = &Sprite> {
+        let touched = self
+            .touched
+            .iter()
+            .filter(move |coord| self.shown.contains(coord) && !self.added.contains(coord))
+            .filter_map(move |coord| self.sprites.get(coord))
+            .filter(|sprite| sprite.is_dirty());
+
+        self.added
+            .iter()
+            .filter_map(move |coord| self.sprites.get(coord))
+            .chain(touched)
+    }
 
-            sprite_image.set_attribute("href", &image)?;
-            sprite_image.set_attribute("width", width.to_string().as_str())?;
-            sprite_image.set_attribute("height", height.to_string().as_str())?;
-            sprite_image.set_attribute("x", (-width / 2.0).to_string().as_str())?;
-            sprite_image.set_attribute("y", (-height / 2.0).to_string().as_str())?;
+    /// Returns the ids of sprites hidden or removed since the layer was last rendered.
+    pub fn removed_sprites(&self) -> &[String] {
+        &self.removed
+    }
 
-            sprite_view.append_child(&sprite_image)?;
+    /// Moves sprites that changed position or visibility in or out of the view. This must be
+    /// done before the layer is rendered.
+    pub fn update_moved(&mut self) {
+        for coord in &self.touched {
+            let sprite = match self.sprites.get(coord) {
+                Some(sprite) if sprite.is_dirty() => sprite,
+                _ => continue,
+            };
+
+            self.index.insert(*coord, &sprite.position);
+            let shown = self.is_shown(sprite);
+
+            if shown && self.shown.insert(*coord) {
+                self.added.insert(*coord);
+            } else if !shown && self.shown.remove(coord) {
+                self.added.remove(coord);
+                self.removed.push(sprite.id.clone());
+            }
         }
+    }
 
-        // All sprite data is defined about the origin.
-        // Move the sprite to the correct location.
-        // FIXME: There is a collision between world space and screen space that needs to be fixed. The sprites are working inside screen space,
-        //        but ideally it needs to work inside world space. The camera needs to operate in world space as well.
-        sprite_view.set_attribute(
-            "transform",
-            format!("translate({},{})", self.position.x, self.position.y).as_str(),
-        )?;
+    /// Forgets all changes once they have been rendered.
+    pub fn mark_clean(&mut self) {
+        for coord in self.added.drain().chain(self.touched.drain()) {
+            if let Some(sprite) = self.sprites.get_mut(&coord) {
+                sprite.dirty = false;
+            }
+        }
+        self.removed.clear();
+        self.restyled = false;
+    }
 
-        debug(format!("sprite rendered: {}", self.id()));
+    /// Returns the top shown sprite covering `point`, with its coord. Sprites are found through
+    /// the spatial index, so the parts of a sprite further than `CULL_MARGIN` from its position
+    /// may be missed, just as they may be culled.
+    pub fn hit_test(&self, point: &Point, cell: &CellGeometry) -> Option<(CellCoord, &Sprite)> {
+        let area = Bounds::new(point.clone(), point.clone()).expand(CULL_MARGIN);
+
+        self.index
+            .query(&area)
+            .filter(|coord| self.shown.contains(coord))
+            .filter_map(|coord| self.sprites.get(coord).map(|sprite| (*coord, sprite)))
+            .filter(|(_, sprite)| sprite.contains(point, cell))
+            .max_by_key(|(_, sprite)| sprite.draw_order())
+    }
 
-        Ok(sprite_view)
+    // Indicates if `sprite` is drawn once the layer is rendered.
+    fn is_shown(&self, sprite: &Sprite) -> bool {
+        sprite.visible && self.in_view(&sprite.position)
+    }
+
+    fn in_view(&self, position: &Point) -> bool {
+        match &self.view {
+            Some(bounds) => SpatialIndex::touches(bounds, position),
+            None => true,
+        }
     }
 }
+
 /*
 pub struct RenderRect {
     point: Point,
@@ -1056,3 +1921,128 @@ impl<C: Cell, L: Layout<C = C>> WorldMap<C, L> {
     }
 }
 */
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zoom_at_01() {
+        let mut camera = Camera::new(100, 80);
+        let point = Point::new(20.0, 10.0);
+
+        camera.zoom_at(2.0, &point);
+
+        assert_eq!(camera.zoom(), 2.0);
+        assert_eq!(camera.view_width(), 50.0);
+        assert_eq!(camera.view_height(), 40.0);
+        // The point is the same fraction of the way across the view as before.
+        assert_eq!((point.x - camera.min_x()) / camera.view_width(), 0.7);
+        assert_eq!((point.y - camera.min_y()) / camera.view_height(), 0.625);
+    }
+
+    #[test]
+    fn zoom_at_02() {
+        let mut camera = Camera::new(100, 80);
+
+        camera.zoom_at(100.0, &Point::origin());
+        assert_eq!(camera.zoom(), MAX_ZOOM);
+
+        camera.zoom_at(0.001, &Point::origin());
+        assert_eq!(camera.zoom(), MIN_ZOOM);
+    }
+
+    #[test]
+    fn bounds_01() {
+        let mut camera = Camera::new(100, 80);
+        camera.set_bounds(Some(Bounds::new(
+            Point::new(-200.0, -100.0),
+            Point::new(200.0, 100.0),
+        )));
+
+        camera.pan(&Point::new(500.0, -500.0));
+        assert_eq!(camera.position, Point::new(150.0, -60.0));
+
+        // Zooming out shows more of the world, so the camera is pushed further in.
+        camera.zoom_at(0.5, &camera.position.clone());
+        assert_eq!(camera.position, Point::new(100.0, -20.0));
+    }
+
+    #[test]
+    fn bounds_02() {
+        let mut camera = Camera::new(100, 80);
+        let bounds = Bounds::new(Point::new(0.0, 0.0), Point::new(40.0, 20.0));
+        camera.set_bounds(Some(bounds.clone()));
+
+        // Bounds smaller than the view keep the camera centered on them.
+        camera.look_at(&Point::new(-30.0, 30.0));
+        assert_eq!(camera.position, bounds.center());
+    }
+
+    #[test]
+    fn contains_01() {
+        let cell = CellGeometry {
+            corners: vec![
+                Point::new(-5.0, -5.0),
+                Point::new(5.0, -5.0),
+                Point::new(5.0, 5.0),
+                Point::new(-5.0, 5.0),
+            ],
+            size: 5.0,
+        };
+        let inside = Point::new(4.0, 4.0);
+        let outside = Point::new(6.0, 0.0);
+
+        assert!(Shape::Cell.contains(&inside, &cell));
+        assert!(!Shape::Cell.contains(&outside, &cell));
+
+        let circle = Shape::Circle { radius: 5 };
+        assert!(circle.contains(&Point::new(3.0, 4.0), &cell));
+        assert!(!circle.contains(&inside, &cell));
+
+        let line = Shape::Polyline {
+            points: vec![Point::new(0.0, 0.0), Point::new(10.0, 0.0)],
+            stroke: Stroke::new(2, "gray"),
+        };
+        assert!(line.contains(&Point::new(5.0, 1.0), &cell));
+        assert!(!line.contains(&Point::new(11.5, 0.0), &cell));
+
+        let text = Shape::text("ab", "sans-serif", 10);
+        assert!(text.contains(&Point::new(5.0, 0.0), &cell));
+        assert!(!text.contains(&Point::new(7.0, 0.0), &cell));
+    }
+
+    #[test]
+    fn contains_02() {
+        let shape = Shape::Rectangle {
+            width: 20,
+            height: 4,
+        };
+        let cell = CellGeometry {
+            corners: Vec::new(),
+            size: 0.0,
+        };
+        let mut sprite = Sprite::new("a", &shape, &Point::new(100.0, 0.0), &Texture::new());
+        assert!(sprite.contains(&Point::new(109.0, 0.0), &cell));
+        assert!(!sprite.contains(&Point::new(100.0, 9.0), &cell));
+
+        // Rotated a quarter turn the rectangle stands upright.
+        sprite.set_rotation(90.0);
+        assert!(!sprite.contains(&Point::new(109.0, 0.0), &cell));
+        assert!(sprite.contains(&Point::new(100.0, 9.0), &cell));
+
+        sprite.set_scale(0.5);
+        assert!(!sprite.contains(&Point::new(100.0, 9.0), &cell));
+        assert!(sprite.contains(&Point::new(100.0, 4.0), &cell));
+    }
+
+    #[test]
+    fn passive_01() {
+        // Only listeners that stop the page from scrolling opt out of being passive. The others
+        // must not opt in, or they could not prevent the context menu.
+        assert_eq!(UserEvent::Wheel.passive(), Some(false));
+        assert_eq!(UserEvent::TouchMove.passive(), Some(false));
+        assert_eq!(UserEvent::ContextMenu.passive(), None);
+        assert_eq!(UserEvent::MouseDown.passive(), None);
+    }
+}
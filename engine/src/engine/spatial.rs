@@ -0,0 +1,122 @@
+use std::collections::{HashMap, HashSet};
+
+use super::cell::CellCoord;
+use super::layout::Point;
+use super::renderer::Bounds;
+
+/// Size in world pixels of the square buckets used to index sprites.
+pub const BUCKET_SIZE: f32 = 128.0;
+
+type Bucket = (i32, i32);
+
+/// Finds the cells whose sprites are near an area without checking every sprite. Cells are put
+/// in square buckets by the position of their sprite, and an area covers every bucket it touches.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    buckets: HashMap<Bucket, HashSet<CellCoord>>,
+    cells: HashMap<CellCoord, Bucket>,
+}
+
+impl SpatialIndex {
+    pub fn new() -> SpatialIndex {
+        SpatialIndex::default()
+    }
+
+    /// Indexes `coord` at `position`, moving it if it was already indexed.
+    pub fn insert(&mut self, coord: CellCoord, position: &Point) {
+        let bucket = bucket(position);
+        if self.cells.get(&coord) == Some(&bucket) {
+            return;
+        }
+
+        self.remove(&coord);
+        self.buckets.entry(bucket).or_default().insert(coord);
+        self.cells.insert(coord, bucket);
+    }
+
+    pub fn remove(&mut self, coord: &CellCoord) {
+        if let Some(bucket) = self.cells.remove(coord) {
+            if let Some(coords) = self.buckets.get_mut(&bucket) {
+                coords.remove(coord);
+                if coords.is_empty() {
+                    self.buckets.remove(&bucket);
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+        self.cells.clear();
+    }
+
+    /// Returns every cell in the buckets touched by `bounds`.
+    pub fn query(&self, bounds: &Bounds) -> impl Iterator<Item = &CellCoord> {
+        let (min, max) = bucket_range(bounds);
+
+        (min.0..=max.0)
+            .flat_map(move |x| (min.1..=max.1).map(move |y| (x, y)))
+            .filter_map(move |bucket| self.buckets.get(&bucket))
+            .flatten()
+    }
+
+    /// Indicates if a sprite at `position` would be returned by `query(bounds)`.
+    pub fn touches(bounds: &Bounds, position: &Point) -> bool {
+        let (min, max) = bucket_range(bounds);
+        let bucket = bucket(position);
+
+        bucket.0 >= min.0 && bucket.0 <= max.0 && bucket.1 >= min.1 && bucket.1 <= max.1
+    }
+}
+
+fn bucket(position: &Point) -> Bucket {
+    (
+        (position.x / BUCKET_SIZE).floor() as i32,
+        (position.y / BUCKET_SIZE).floor() as i32,
+    )
+}
+
+fn bucket_range(bounds: &Bounds) -> (Bucket, Bucket) {
+    (bucket(&bounds.min), bucket(&bounds.max))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn coord(x: i32) -> CellCoord {
+        CellCoord::new(x, -x, 0)
+    }
+
+    #[test]
+    fn query_01() {
+        let mut index = SpatialIndex::new();
+        index.insert(coord(0), &Point::new(10.0, 10.0));
+        index.insert(coord(1), &Point::new(-10.0, 10.0));
+        index.insert(coord(2), &Point::new(1000.0, 10.0));
+
+        let bounds = Bounds::new(Point::new(0.0, 0.0), Point::new(100.0, 100.0));
+        let found: HashSet<&CellCoord> = index.query(&bounds).collect();
+
+        assert_eq!(found, [coord(0)].iter().collect());
+        assert!(SpatialIndex::touches(&bounds, &Point::new(120.0, 0.0)));
+        assert!(!SpatialIndex::touches(&bounds, &Point::new(-1.0, 0.0)));
+    }
+
+    #[test]
+    fn insert_01() {
+        let mut index = SpatialIndex::new();
+        let bounds = Bounds::new(Point::new(0.0, 0.0), Point::new(100.0, 100.0));
+        index.insert(coord(0), &Point::new(10.0, 10.0));
+
+        // Inserting again moves the cell.
+        index.insert(coord(0), &Point::new(1000.0, 10.0));
+        assert_eq!(index.query(&bounds).count(), 0);
+
+        index.insert(coord(0), &Point::new(50.0, 10.0));
+        assert_eq!(index.query(&bounds).count(), 1);
+
+        index.remove(&coord(0));
+        assert_eq!(index.query(&bounds).count(), 0);
+    }
+}
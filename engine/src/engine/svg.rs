@@ -0,0 +1,803 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use super::atlas::{TextureAtlas, TextureDef};
+use super::layout::Point;
+use super::renderer::{
+    create_svg_element, get_target, Backend, Camera, CellGeometry, DrawOrder, Layer, RenderError,
+    Shape, Sprite, Texture,
+};
+
+use web_sys::{Element, SvgsvgElement};
+
+use wasm_bindgen::JsCast;
+
+/// Renders sprites as SVG elements inside a target element. Elements are kept between renders
+/// and only the sprites that changed are replaced.
+pub struct SvgBackend {
+    svg_view: Element,
+    layer_views: Vec<LayerView>,
+    atlas: TextureAtlas,
+    // Definitions of the atlas's textures, kept as the first child of the SVG element.
+    defs: Option<Element>,
+}
+
+// The elements drawn for a layer. Sprite elements are kept in drawing order, since later
+// elements are drawn over earlier ones.
+struct LayerView {
+    name: String,
+    element: Element,
+    sprites: HashMap<String, (DrawOrder, Element)>,
+    order: BTreeMap<DrawOrder, String>,
+}
+
+impl SvgBackend {
+    pub fn new(target_id: &str, width: i32, height: i32) -> Result<SvgBackend, RenderError> {
+        // Setup the world render target. This must be done only once.
+        let target = get_target(target_id)?;
+        let render_camera = Camera::new(width, height);
+        let svg_view = create_svg(&render_camera)?;
+        svg_view.set_attribute("id", format!("{}_svg", target_id).as_str())?;
+        target.append_child(&svg_view)?;
+
+        Ok(SvgBackend {
+            svg_view,
+            layer_views: Vec::new(),
+            atlas: TextureAtlas::new(),
+            defs: None,
+        })
+    }
+
+    // Returns the position of the view for `layer_name`, creating it if it does not exist.
+    // New views are placed in the same order as `layers`.
+    fn layer_view(&mut self, layers: &[Layer], layer_name: &str) -> Result<usize, RenderError> {
+        if let Some(index) = self
+            .layer_views
+            .iter()
+            .position(|view| view.name == layer_name)
+        {
+            return Ok(index);
+        }
+
+        let element = create_svg_element("g")?;
+        element.set_attribute("id", layer_name)?;
+
+        // Insert before the view of the first layer above this one.
+        let above: Vec<&str> = layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .skip_while(|name| *name != layer_name)
+            .skip(1)
+            .collect();
+        let index = self
+            .layer_views
+            .iter()
+            .position(|view| above.contains(&view.name.as_str()))
+            .unwrap_or(self.layer_views.len());
+
+        match self.layer_views.get(index) {
+            Some(next) => {
+                self.svg_view.insert_before(&element, Some(&next.element))?;
+            }
+            None => {
+                self.svg_view.append_child(&element)?;
+            }
+        }
+
+        self.layer_views.insert(
+            index,
+            LayerView {
+                name: layer_name.to_owned(),
+                element,
+                sprites: HashMap::new(),
+                order: BTreeMap::new(),
+            },
+        );
+
+        Ok(index)
+    }
+}
+
+impl LayerView {
+    fn update(
+        &mut self,
+        layer: &Layer,
+        cell: &CellGeometry,
+        atlas: &TextureAtlas,
+    ) -> Result<(), RenderError> {
+        // A hidden layer keeps its sprites so showing it again draws nothing new.
+        if layer.is_visible() {
+            self.element.remove_attribute("display")?;
+        } else {
+            self.element.set_attribute("display", "none")?;
+        }
+        if layer.opacity() < 1.0 {
+            self.element
+                .set_attribute("opacity", layer.opacity().to_string().as_str())?;
+        } else {
+            self.element.remove_attribute("opacity")?;
+        }
+
+        for sprite_id in layer.removed_sprites() {
+            self.remove_sprite(sprite_id);
+        }
+
+        for sprite in layer.changed_sprites() {
+            let sprite_view = render_sprite(sprite, cell, atlas)?;
+            let draw_order = sprite.draw_order();
+
+            match self.sprites.get(sprite.id()) {
+                // Replace the sprite in place if it did not move in the drawing order.
+                Some((old_order, old_view)) if *old_order == draw_order => {
+                    old_view.replace_with_with_node_1(&sprite_view)?;
+                }
+                _ => {
+                    self.remove_sprite(sprite.id());
+
+                    // Add the sprite to the layer before the first sprite drawn after it.
+                    let next = self
+                        .order
+                        .range(draw_order..)
+                        .next()
+                        .map(|(_, next_id)| self.sprites[next_id].1.clone());
+                    match next {
+                        Some(next) => {
+                            self.element.insert_before(&sprite_view, Some(&next))?;
+                        }
+                        None => {
+                            self.element.append_child(&sprite_view)?;
+                        }
+                    }
+                    self.order.insert(draw_order, sprite.id().to_owned());
+                }
+            }
+
+            self.sprites
+                .insert(sprite.id().to_owned(), (draw_order, sprite_view));
+        }
+
+        Ok(())
+    }
+
+    fn remove_sprite(&mut self, sprite_id: &str) {
+        if let Some((draw_order, sprite_view)) = self.sprites.remove(sprite_id) {
+            self.order.remove(&draw_order);
+            // Remove the sprite from the DOM.
+            sprite_view.remove();
+        }
+    }
+}
+
+impl Backend for SvgBackend {
+    fn look_at(&mut self, camera: &Camera) -> Result<(), RenderError> {
+        self.svg_view
+            .set_attribute("viewBox", view_box(camera).as_str())?;
+
+        Ok(())
+    }
+
+    fn render(&mut self, layers: &[Layer], cell: &CellGeometry) -> Result<(), RenderError> {
+        // Remove the views of layers that no longer exist.
+        self.layer_views.retain(|view| {
+            let exists = layers.iter().any(|layer| layer.name == view.name);
+            if !exists {
+                view.element.remove();
+            }
+            exists
+        });
+
+        // Put the views of moved layers back in the order of the layers. Appending an element
+        // moves it to the end.
+        let position = |name: &str| layers.iter().position(|layer| layer.name == name);
+        let in_order = self
+            .layer_views
+            .windows(2)
+            .all(|pair| position(&pair[0].name) < position(&pair[1].name));
+        if !in_order {
+            self.layer_views.sort_by_key(|view| position(&view.name));
+            for view in &self.layer_views {
+                self.svg_view.append_child(&view.element)?;
+            }
+        }
+
+        for layer in layers {
+            if !layer.is_dirty() {
+                continue;
+            }
+
+            let index = self.layer_view(layers, &layer.name)?;
+            self.layer_views[index].update(layer, cell, &self.atlas)?;
+        }
+
+        Ok(())
+    }
+
+    fn set_atlas(&mut self, atlas: &TextureAtlas) -> Result<(), RenderError> {
+        let defs = atlas.defs().to_element()?;
+        match &self.defs {
+            Some(old_defs) => old_defs.replace_with_with_node_1(&defs)?,
+            None => {
+                let first = self.svg_view.first_child();
+                self.svg_view.insert_before(&defs, first.as_ref())?;
+            }
+        }
+
+        self.defs = Some(defs);
+        self.atlas = atlas.clone();
+
+        Ok(())
+    }
+
+    fn client_point(&self, client: &Point) -> Result<Point, RenderError> {
+        let svg_view = self.svg_view.clone().dyn_into::<SvgsvgElement>()?;
+
+        // Get point in global SVG space. There is no screen matrix while the SVG is not shown,
+        // and none to invert while it is scaled to nothing.
+        let svg_matrix = svg_view
+            .get_screen_ctm()
+            .ok_or_else(|| RenderError::new("svg is not shown on screen"))?
+            .inverse()?;
+        let svg_point = svg_view.create_svg_point();
+        svg_point.set_x(client.x);
+        svg_point.set_y(client.y);
+        let svg_point = svg_point.matrix_transform(&svg_matrix);
+
+        Ok(Point::new(svg_point.x(), svg_point.y()))
+    }
+}
+
+/// Writes `layers`, as seen by `camera`, out as a standalone SVG document. The textures of
+/// `atlas` are defined first, then each layer and sprite is on its own line, in the order they
+/// are drawn.
+pub fn export_svg(
+    camera: &Camera,
+    layers: &[Layer],
+    cell: &CellGeometry,
+    atlas: &TextureAtlas,
+) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{}\">\n",
+        camera.width,
+        camera.height,
+        view_box(camera)
+    );
+
+    if !atlas.is_empty() {
+        svg.push_str(&format!("{}\n", atlas.defs()));
+    }
+
+    for layer in layers {
+        svg.push_str(&layer_start_tag(
+            &layer.name,
+            layer.is_visible(),
+            layer.opacity(),
+        ));
+        for sprite in layer.ordered_sprites() {
+            if !sprite.is_visible() {
+                continue;
+            }
+
+            svg.push_str(&format!("{}\n", sprite_node(sprite, cell, atlas)));
+        }
+        svg.push_str("</g>\n");
+    }
+
+    svg.push_str("</svg>\n");
+
+    svg
+}
+
+/// Returns the line starting the group of a layer in a standalone SVG document.
+pub fn layer_start_tag(name: &str, visible: bool, opacity: f32) -> String {
+    let mut tag = format!("<g id=\"{}\"", escape(name));
+    if opacity < 1.0 {
+        tag.push_str(&format!(" opacity=\"{}\"", opacity));
+    }
+    if !visible {
+        tag.push_str(" display=\"none\"");
+    }
+    tag.push_str(">\n");
+
+    tag
+}
+
+pub fn create_svg(camera: &Camera) -> Result<Element, RenderError> {
+    let svg_view = create_svg_element("svg")?;
+
+    svg_view.set_attribute("viewBox", view_box(camera).as_str())?;
+
+    Ok(svg_view)
+}
+
+pub fn view_box(camera: &Camera) -> String {
+    format!(
+        "{} {} {} {}",
+        camera.min_x(),
+        camera.min_y(),
+        camera.view_width(),
+        camera.view_height()
+    )
+}
+
+/// An SVG element described without the DOM, so sprites can be drawn into a document or
+/// written out as markup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgNode {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<SvgNode>,
+    /// Text inside the element, written after the children.
+    pub text: Option<String>,
+}
+
+impl SvgNode {
+    pub fn new(name: &str) -> SvgNode {
+        SvgNode {
+            name: name.to_owned(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+            text: None,
+        }
+    }
+
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        self.attributes.push((name.to_owned(), value.to_owned()));
+    }
+
+    pub fn append_child(&mut self, child: SvgNode) {
+        self.children.push(child);
+    }
+
+    /// Creates the DOM element for the node and all of its children.
+    pub fn to_element(&self) -> Result<Element, RenderError> {
+        let element = create_svg_element(&self.name)?;
+
+        for (name, value) in &self.attributes {
+            element.set_attribute(name, value)?;
+        }
+
+        for child in &self.children {
+            let child_element = child.to_element()?;
+            element.append_child(&child_element)?;
+        }
+
+        if let Some(text) = &self.text {
+            element.append_with_str_1(text)?;
+        }
+
+        Ok(element)
+    }
+}
+
+impl fmt::Display for SvgNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}", self.name)?;
+        for (name, value) in &self.attributes {
+            write!(f, " {}=\"{}\"", name, escape(value))?;
+        }
+
+        if self.children.is_empty() && self.text.is_none() {
+            return write!(f, "/>");
+        }
+
+        write!(f, ">")?;
+        for child in &self.children {
+            write!(f, "{}", child)?;
+        }
+        if let Some(text) = &self.text {
+            write!(f, "{}", escape(text))?;
+        }
+        write!(f, "</{}>", self.name)
+    }
+}
+
+// Escapes the characters that cannot appear as they are in SVG text or attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Returns the `d` attribute of a path through `points`. Each corner is rounded with a quadratic
+// curve from the middle of the line before it to the middle of the line after it.
+fn path_data(points: &[Point]) -> String {
+    let mut data = String::new();
+
+    for (i, point) in points.iter().enumerate() {
+        if i == 0 {
+            data.push_str(&format!("M{}", String::from(point)));
+        } else if i == points.len() - 1 {
+            data.push_str(&format!(" L{}", String::from(point)));
+        } else {
+            if i == 1 {
+                let before = &(&points[0] + point) / 2.0;
+                data.push_str(&format!(" L{}", String::from(&before)));
+            }
+            let after = &(point + &points[i + 1]) / 2.0;
+            data.push_str(&format!(
+                " Q{} {}",
+                String::from(point),
+                String::from(&after)
+            ));
+        }
+    }
+
+    data
+}
+
+// Returns the style of a texture. A pattern or gradient from the atlas fills the shape instead of
+// the background color.
+fn texture_style(texture: &Texture, atlas: &TextureAtlas) -> String {
+    let shared = texture
+        .name
+        .as_ref()
+        .and_then(|name| atlas.get(name).map(|def| (name, def)));
+
+    match shared {
+        Some((name, TextureDef::Pattern { .. })) | Some((name, TextureDef::Gradient { .. })) => {
+            let mut style = format!("fill:url(#{});", TextureAtlas::def_id(name));
+            if let Some(border) = &texture.border {
+                style.push_str(&border.style_str());
+            }
+            style
+        }
+        _ => texture.style_str(),
+    }
+}
+
+fn render_sprite(
+    sprite: &Sprite,
+    cell: &CellGeometry,
+    atlas: &TextureAtlas,
+) -> Result<Element, RenderError> {
+    sprite_node(sprite, cell, atlas).to_element()
+}
+
+/// Describes the SVG group drawn for `sprite`.
+pub fn sprite_node(sprite: &Sprite, cell: &CellGeometry, atlas: &TextureAtlas) -> SvgNode {
+    // Group all of a sprites data together.
+    let mut sprite_view = SvgNode::new("g");
+
+    sprite_view.set_attribute("id", sprite.id());
+
+    // A sprite is defined as a polygon of any shape.
+    let width;
+    let height;
+
+    let mut sprite_polygon = SvgNode::new(sprite.shape().svg_name());
+
+    let mut style = texture_style(sprite.texture(), atlas);
+
+    match sprite.shape() {
+        Shape::Cell => {
+            let mut corners_string: String = "".to_owned();
+            for corner in &cell.corners {
+                corners_string.push_str(String::from(corner).as_str());
+                corners_string.push(' ');
+            }
+
+            sprite_polygon.set_attribute("points", corners_string.as_str());
+
+            width = cell.size;
+            height = cell.size;
+        }
+        Shape::Rectangle {
+            width: rect_width,
+            height: rect_height,
+        } => {
+            sprite_polygon.set_attribute("width", rect_width.to_string().as_str());
+            sprite_polygon.set_attribute("height", rect_height.to_string().as_str());
+            sprite_polygon.set_attribute("x", (-rect_width as f32 / 2.0).to_string().as_str());
+            sprite_polygon.set_attribute("y", (-rect_height as f32 / 2.0).to_string().as_str());
+
+            width = *rect_width as f32;
+            height = *rect_height as f32;
+        }
+        Shape::Circle { radius } => {
+            sprite_polygon.set_attribute("cx", "0");
+            sprite_polygon.set_attribute("cy", "0");
+            sprite_polygon.set_attribute("r", radius.to_string().as_str());
+
+            width = *radius as f32;
+            height = *radius as f32;
+        }
+        Shape::Text {
+            text,
+            font,
+            size,
+            anchor,
+        } => {
+            sprite_polygon.set_attribute("font-family", font);
+            sprite_polygon.set_attribute("font-size", size.to_string().as_str());
+            sprite_polygon.set_attribute("text-anchor", anchor.svg_name());
+            sprite_polygon.set_attribute("dominant-baseline", "middle");
+            sprite_polygon.text = Some(text.to_owned());
+
+            // Text is black unless it is given a color.
+            if sprite.texture().background_color.is_none() {
+                style = style.replace("fill-opacity: 0.0;", "fill:black;");
+            }
+
+            width = *size as f32;
+            height = *size as f32;
+        }
+        Shape::Polyline { points, stroke } | Shape::Path { points, stroke } => {
+            if let Shape::Path { .. } = sprite.shape() {
+                sprite_polygon.set_attribute("d", path_data(points).as_str());
+            } else {
+                let points: Vec<String> = points.iter().map(String::from).collect();
+                sprite_polygon.set_attribute("points", points.join(" ").as_str());
+            }
+
+            // Lines are drawn with their own stroke instead of the texture.
+            style = stroke.style_str();
+
+            width = 0.0;
+            height = 0.0;
+        }
+    };
+
+    sprite_polygon.set_attribute("style", style.as_str());
+
+    // Add the polygon shape to the sprite group.
+    sprite_view.append_child(sprite_polygon);
+
+    if let Shape::Polyline { points, stroke } | Shape::Path { points, stroke } = sprite.shape() {
+        for arrowhead in stroke.arrowheads(points) {
+            let corners: Vec<String> = arrowhead.iter().map(String::from).collect();
+
+            let mut arrowhead_node = SvgNode::new("polygon");
+            arrowhead_node.set_attribute("points", corners.join(" ").as_str());
+            arrowhead_node.set_attribute("style", format!("fill:{};", stroke.color).as_str());
+
+            sprite_view.append_child(arrowhead_node);
+        }
+    }
+
+    // Browsers show a <title> child as a tooltip when hovering the group.
+    if let Some(tooltip) = sprite.tooltip() {
+        let mut title = SvgNode::new("title");
+        title.text = Some(tooltip.to_owned());
+
+        sprite_view.append_child(title);
+    }
+
+    let symbol = sprite
+        .texture()
+        .name
+        .as_ref()
+        .filter(|name| matches!(atlas.get(name), Some(TextureDef::Symbol { .. })));
+
+    // Set any texture for the sprite as an <image> child of the sprite group, or as a <use> of
+    // the atlas's symbol so identical sprites share one image.
+    if let Some(name) = symbol {
+        let mut sprite_use = SvgNode::new("use");
+        sprite_use.set_attribute("href", format!("#{}", TextureAtlas::def_id(name)).as_str());
+
+        sprite_view.append_child(sprite_use);
+    } else if let Some(image) = &sprite.texture().image {
+        let mut sprite_image = SvgNode::new("image");
+
+        sprite_image.set_attribute("href", image);
+        sprite_image.set_attribute("width", width.to_string().as_str());
+        sprite_image.set_attribute("height", height.to_string().as_str());
+        sprite_image.set_attribute("x", (-width / 2.0).to_string().as_str());
+        sprite_image.set_attribute("y", (-height / 2.0).to_string().as_str());
+
+        sprite_view.append_child(sprite_image);
+    }
+
+    // All sprite data is defined about the origin.
+    // Move the sprite to the correct location.
+    // FIXME: There is a collision between world space and screen space that needs to be fixed. The sprites are working inside screen space,
+    //        but ideally it needs to work inside world space. The camera needs to operate in world space as well.
+    let mut transform = format!("translate({},{})", sprite.position().x, sprite.position().y);
+    if sprite.rotation() != 0.0 {
+        transform.push_str(&format!(" rotate({})", sprite.rotation()));
+    }
+    if sprite.scale() != 1.0 {
+        transform.push_str(&format!(" scale({})", sprite.scale()));
+    }
+    sprite_view.set_attribute("transform", transform.as_str());
+
+    if sprite.opacity() < 1.0 {
+        sprite_view.set_attribute("opacity", sprite.opacity().to_string().as_str());
+    }
+
+    sprite_view
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::cell::CellCoord;
+    use super::super::renderer::{Stroke, TextureBorder};
+    use super::*;
+
+    #[test]
+    fn export_svg_01() {
+        let camera = Camera::new(100, 80);
+        let cell = CellGeometry {
+            corners: vec![
+                Point::new(-5.0, -5.0),
+                Point::new(5.0, -5.0),
+                Point::new(5.0, 5.0),
+                Point::new(-5.0, 5.0),
+            ],
+            size: 5.0,
+        };
+
+        let mut texture = Texture::new();
+        texture.image = Some("belt.png".to_owned());
+        let mut ground = Layer::new("ground");
+        ground.add_sprite(
+            CellCoord::new(1, 0, 0),
+            Sprite::new("b", &Shape::Cell, &Point::new(10.0, 0.0), &texture),
+        );
+        ground.add_sprite(
+            CellCoord::new(0, 0, 0),
+            Sprite::new(
+                "a",
+                &Shape::Circle { radius: 2 },
+                &Point::origin(),
+                &Texture::new(),
+            ),
+        );
+        let layers = vec![ground, Layer::new("items")];
+
+        assert_eq!(
+            export_svg(&camera, &layers, &cell, &TextureAtlas::new()),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"80\" viewBox=\"-50 -40 100 80\">\n\
+             <g id=\"ground\">\n\
+             <g id=\"b\" transform=\"translate(10,0)\">\
+             <polygon points=\"-5,-5 5,-5 5,5 -5,5 \" style=\"fill-opacity: 0.0;\"/>\
+             <image href=\"belt.png\" width=\"5\" height=\"5\" x=\"-2.5\" y=\"-2.5\"/></g>\n\
+             <g id=\"a\" transform=\"translate(0,0)\">\
+             <circle cx=\"0\" cy=\"0\" r=\"2\" style=\"fill-opacity: 0.0;\"/></g>\n\
+             </g>\n\
+             <g id=\"items\">\n\
+             </g>\n\
+             </svg>\n"
+        );
+    }
+
+    #[test]
+    fn layer_start_tag_01() {
+        assert_eq!(
+            layer_start_tag("a\"<b>&c", false, 0.5),
+            "<g id=\"a&quot;&lt;b&gt;&amp;c\" opacity=\"0.5\" display=\"none\">\n"
+        );
+    }
+
+    #[test]
+    fn sprite_node_01() {
+        let cell = CellGeometry {
+            corners: Vec::new(),
+            size: 0.0,
+        };
+        let mut sprite = Sprite::new(
+            "a",
+            &Shape::Circle { radius: 2 },
+            &Point::new(5.0, 0.0),
+            &Texture::new(),
+        );
+        sprite.set_rotation(60.0);
+        sprite.set_scale(1.5);
+        sprite.set_opacity(0.5);
+
+        assert_eq!(
+            sprite_node(&sprite, &cell, &TextureAtlas::new()).to_string(),
+            "<g id=\"a\" transform=\"translate(5,0) rotate(60) scale(1.5)\" opacity=\"0.5\">\
+             <circle cx=\"0\" cy=\"0\" r=\"2\" style=\"fill-opacity: 0.0;\"/></g>"
+        );
+    }
+
+    #[test]
+    fn sprite_node_02() {
+        let cell = CellGeometry {
+            corners: Vec::new(),
+            size: 0.0,
+        };
+        let mut sprite = Sprite::new(
+            "label",
+            &Shape::text("Working", "sans-serif", 8),
+            &Point::new(0.0, 10.0),
+            &Texture::new(),
+        );
+        sprite.set_tooltip(Some("Buffer: 3 < 10"));
+
+        assert_eq!(
+            sprite_node(&sprite, &cell, &TextureAtlas::new()).to_string(),
+            "<g id=\"label\" transform=\"translate(0,10)\">\
+             <text font-family=\"sans-serif\" font-size=\"8\" text-anchor=\"middle\" \
+             dominant-baseline=\"middle\" style=\"fill:black;\">Working</text>\
+             <title>Buffer: 3 &lt; 10</title></g>"
+        );
+    }
+
+    #[test]
+    fn sprite_node_03() {
+        let cell = CellGeometry {
+            corners: Vec::new(),
+            size: 0.0,
+        };
+        let mut stroke = Stroke::new(2, "gray");
+        stroke.dash = vec![4, 2];
+        stroke.end_arrow = true;
+        let shape = Shape::Polyline {
+            points: vec![Point::new(-10.0, 0.0), Point::new(10.0, 0.0)],
+            stroke,
+        };
+        let sprite = Sprite::new("belt", &shape, &Point::origin(), &Texture::new());
+
+        assert_eq!(
+            sprite_node(&sprite, &cell, &TextureAtlas::new()).to_string(),
+            "<g id=\"belt\" transform=\"translate(0,0)\">\
+             <polyline points=\"-10,0 10,0\" style=\"fill:none;stroke:gray;stroke-width:2;\
+             stroke-linecap:round;stroke-linejoin:round;stroke-dasharray:4,2;\"/>\
+             <polygon points=\"10,0 4,3 4,-3\" style=\"fill:gray;\"/></g>"
+        );
+    }
+
+    #[test]
+    fn path_data_01() {
+        let points = vec![
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+        ];
+
+        assert_eq!(path_data(&points), "M0,0 L5,0 Q10,0 10,5 L10,10");
+        assert_eq!(path_data(&points[..2]), "M0,0 L10,0");
+    }
+
+    #[test]
+    fn sprite_node_04() {
+        let cell = CellGeometry {
+            corners: Vec::new(),
+            size: 0.0,
+        };
+        let mut atlas = TextureAtlas::new();
+        atlas.insert(
+            "factory",
+            TextureDef::Symbol {
+                image: "factory.svg".to_owned(),
+                width: 15,
+                height: 15,
+            },
+        );
+        atlas.insert(
+            "belt",
+            TextureDef::Gradient {
+                stops: vec![(0.0, "gray".to_owned()), (1.0, "dimgray".to_owned())],
+            },
+        );
+        let shape = Shape::Circle { radius: 2 };
+
+        let factory = Sprite::new("f", &shape, &Point::origin(), &Texture::named("factory"));
+        assert_eq!(
+            sprite_node(&factory, &cell, &atlas).to_string(),
+            "<g id=\"f\" transform=\"translate(0,0)\">\
+             <circle cx=\"0\" cy=\"0\" r=\"2\" style=\"fill-opacity: 0.0;\"/>\
+             <use href=\"#texture_factory\"/></g>"
+        );
+
+        let mut texture = Texture::named("belt");
+        texture.border = Some(TextureBorder::new(1, "black"));
+        let belt = Sprite::new("b", &shape, &Point::origin(), &texture);
+        assert_eq!(
+            sprite_node(&belt, &cell, &atlas).to_string(),
+            "<g id=\"b\" transform=\"translate(0,0)\">\
+             <circle cx=\"0\" cy=\"0\" r=\"2\" \
+             style=\"fill:url(#texture_belt);stroke:black;stroke-width:1;\"/></g>"
+        );
+
+        // Names missing from the atlas are ignored.
+        let missing = Sprite::new("m", &shape, &Point::origin(), &Texture::named("water"));
+        assert_eq!(
+            sprite_node(&missing, &cell, &atlas).to_string(),
+            "<g id=\"m\" transform=\"translate(0,0)\">\
+             <circle cx=\"0\" cy=\"0\" r=\"2\" style=\"fill-opacity: 0.0;\"/></g>"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+// Touches move the camera like a map: one finger pans it and two fingers pinch to zoom it. Each
+// finger holds on to the world point it first touched, and the camera is moved so that point
+// stays under the finger.
+
+use std::collections::BTreeMap;
+
+use super::layout::Point;
+
+/// How to move the camera so the touched points stay under the fingers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GestureChange {
+    /// Factor to zoom the camera by about `center`.
+    pub zoom: f32,
+    /// Where the fingers are now, in world space.
+    pub center: Point,
+    /// Distance to pan the camera by after zooming.
+    pub pan: Point,
+}
+
+/// Tracks the fingers touching the world.
+#[derive(Debug, Clone, Default)]
+pub struct TouchGesture {
+    // World point first touched by each finger, by touch identifier.
+    anchors: BTreeMap<i32, Point>,
+}
+
+impl TouchGesture {
+    pub fn new() -> TouchGesture {
+        TouchGesture::default()
+    }
+
+    /// Holds on to the world points under `touches`, given by touch identifier. Call this
+    /// whenever a finger is added or lifted, with every finger still touching.
+    pub fn start(&mut self, touches: &[(i32, Point)]) {
+        self.anchors = touches.iter().cloned().collect();
+    }
+
+    /// Lets go of every finger.
+    pub fn end(&mut self) {
+        self.anchors.clear();
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.anchors.is_empty()
+    }
+
+    /// Returns how to move the camera for the fingers to be at `touches` in world space, or
+    /// `None` if none of them were held. Only the first two fingers are followed.
+    pub fn update(&self, touches: &[(i32, Point)]) -> Option<GestureChange> {
+        let (anchors, points): (Vec<Point>, Vec<Point>) = touches
+            .iter()
+            .filter_map(|(id, point)| {
+                let anchor = self.anchors.get(id)?;
+                Some((anchor.clone(), point.clone()))
+            })
+            .take(2)
+            .unzip();
+
+        let anchor_center = center(&anchors)?;
+        let center = center(&points)?;
+
+        // Zooming in shrinks the distance between the fingers in world space, so zoom by how far
+        // they moved apart.
+        let zoom = match (anchors.as_slice(), points.as_slice()) {
+            ([a0, a1], [p0, p1]) if distance(a0, a1) > 0.0 => distance(p0, p1) / distance(a0, a1),
+            _ => 1.0,
+        };
+
+        Some(GestureChange {
+            zoom,
+            pan: &anchor_center - &center,
+            center,
+        })
+    }
+}
+
+fn center(points: &[Point]) -> Option<Point> {
+    if points.is_empty() {
+        return None;
+    }
+
+    let count = points.len() as f32;
+    let sum = points
+        .iter()
+        .fold(Point::origin(), |sum, point| &sum + point);
+
+    Some(Point::new(sum.x / count, sum.y / count))
+}
+
+fn distance(a: &Point, b: &Point) -> f32 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    (dx * dx + dy * dy).sqrt()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pan_01() {
+        let mut gesture = TouchGesture::new();
+        assert_eq!(gesture.update(&[(0, Point::origin())]), None);
+
+        gesture.start(&[(0, Point::new(10.0, 10.0))]);
+        assert!(gesture.is_active());
+
+        // Dragging a finger right pans the camera left.
+        assert_eq!(
+            gesture.update(&[(0, Point::new(15.0, 10.0))]),
+            Some(GestureChange {
+                zoom: 1.0,
+                center: Point::new(15.0, 10.0),
+                pan: Point::new(-5.0, 0.0),
+            })
+        );
+
+        gesture.end();
+        assert!(!gesture.is_active());
+        assert_eq!(gesture.update(&[(0, Point::origin())]), None);
+    }
+
+    #[test]
+    fn pinch_01() {
+        let mut gesture = TouchGesture::new();
+        gesture.start(&[(3, Point::new(-10.0, 0.0)), (7, Point::new(10.0, 0.0))]);
+
+        // Spreading the fingers apart zooms in.
+        let change = gesture
+            .update(&[(3, Point::new(-20.0, 5.0)), (7, Point::new(20.0, 5.0))])
+            .unwrap();
+        assert_eq!(change.zoom, 2.0);
+        assert_eq!(change.center, Point::new(0.0, 5.0));
+        assert_eq!(change.pan, Point::new(0.0, -5.0));
+
+        // Fingers that were not held are ignored.
+        let change = gesture
+            .update(&[(3, Point::new(-10.0, 0.0)), (9, Point::new(50.0, 0.0))])
+            .unwrap();
+        assert_eq!(change.zoom, 1.0);
+    }
+}
@@ -2,19 +2,29 @@ use std::cmp;
 use std::cmp::{Ord, Ordering, Reverse};
 use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 
+use super::animation::{Animation, Animator};
 use super::cell::{Cell, CellCoord};
 use super::layout::{Layout, Point, Rectangle};
 use super::logging::{debug, info};
+use super::minimap;
 use super::renderer::{
-    add_event, add_key_event, add_mouse_event, create_svg, get_body, get_target, Camera, Layer,
-    RenderError, Renderable, UserEvent, Viewport,
+    add_event, add_key_event, add_mouse_event, add_pointer_event, add_touch_event, add_wheel_event,
+    get_body, get_target, Backend, Bounds, Camera, EventListener, Hit, Layer, RenderError,
+    UserEvent, Viewport,
 };
+use super::touch::TouchGesture;
 
-use web_sys::{Document, Element, Event, MouseEvent, SvgElement, SvgsvgElement};
+use web_sys::{
+    Document, Element, Event, KeyboardEvent, MouseEvent, PointerEvent, SvgElement, SvgsvgElement,
+    TouchEvent, WheelEvent,
+};
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::*;
 
+/// Number of simulation ticks the game runs each second of real time.
+pub const TICKS_PER_SECOND: usize = 60;
+
 // TODO: This will eventually be more complex and involve movement costs, etc.
 type Collisions = HashSet<CellCoord>;
 
@@ -28,6 +38,26 @@ where
     pub viewport: Viewport,
     cameras: Vec<Camera>,
     active_camera: usize,
+    views: Vec<View>,
+    // Id of the view used as the minimap.
+    minimap: Option<usize>,
+    // World point held under the mouse while dragging the camera.
+    drag_anchor: Option<Point>,
+    touches: TouchGesture,
+    animator: Animator,
+}
+
+/// A viewport that shows the world through one of the cameras. The main viewport always shows
+/// the active camera.
+pub struct View {
+    pub viewport: Viewport,
+    camera: usize,
+}
+
+impl View {
+    pub fn camera(&self) -> usize {
+        self.camera
+    }
 }
 
 impl<C, L> World<C, L>
@@ -47,9 +77,35 @@ where
             viewport: Viewport::new(target_id, width, height)?,
             cameras: vec![Camera::new(width, height)],
             active_camera: 0,
+            views: Vec::new(),
+            minimap: None,
+            drag_anchor: None,
+            touches: TouchGesture::new(),
+            animator: Animator::new(),
         })
     }
 
+    /// Creates a world that renders with `backend`, such as a `CanvasBackend`.
+    pub fn with_backend(
+        backend: Box<dyn Backend>,
+        layout: L,
+        width: i32,
+        height: i32,
+    ) -> World<C, L> {
+        World {
+            base_map: HashSet::new(),
+            layout: layout,
+            viewport: Viewport::with_backend(backend),
+            cameras: vec![Camera::new(width, height)],
+            active_camera: 0,
+            views: Vec::new(),
+            minimap: None,
+            drag_anchor: None,
+            touches: TouchGesture::new(),
+            animator: Animator::new(),
+        }
+    }
+
     pub fn camera(&self, id: usize) -> &Camera {
         &self.cameras[id]
     }
@@ -58,6 +114,12 @@ where
         &mut self.cameras[id]
     }
 
+    /// Adds a camera and returns its id.
+    pub fn add_camera(&mut self, camera: Camera) -> usize {
+        self.cameras.push(camera);
+        self.cameras.len() - 1
+    }
+
     pub fn active_camera(&self) -> usize {
         self.active_camera
     }
@@ -69,14 +131,251 @@ where
 
         self.active_camera = id;
 
-        Ok(())
+        self.update_camera()
     }
 
     pub fn look_at(&mut self, position: &Point) -> Result<(), RenderError> {
-        // FIXME: This is awkward.
         self.camera_mut(self.active_camera()).look_at(position);
-        let camera = self.camera_mut(self.active_camera()).clone();
+        self.update_camera()
+    }
+
+    /// Moves the active camera by `delta` in world space.
+    pub fn pan(&mut self, delta: &Point) -> Result<(), RenderError> {
+        self.camera_mut(self.active_camera()).pan(delta);
+        self.update_camera()
+    }
+
+    /// Scales the active camera's view by `factor` about `point` in world space.
+    pub fn zoom_at(&mut self, factor: f32, point: &Point) -> Result<(), RenderError> {
+        self.camera_mut(self.active_camera()).zoom_at(factor, point);
+        self.update_camera()
+    }
+
+    /// Scales the active camera's view by `factor` about its center.
+    pub fn zoom(&mut self, factor: f32) -> Result<(), RenderError> {
+        let center = self.camera(self.active_camera()).position.clone();
+        self.zoom_at(factor, &center)
+    }
+
+    /// Returns the rotation in degrees that turns a sprite facing right to face the neighbouring
+    /// cell in `direction`, such as one of the six directions of a hex.
+    pub fn direction_rotation(&self, direction: i32) -> f32 {
+        let cell = self.layout.pixel_to_cell(self.layout.origin());
+        let delta = &self.layout.cell_to_pixel(&cell.neighbor(direction))
+            - &self.layout.cell_to_pixel(&cell);
+
+        delta.y.atan2(delta.x).to_degrees()
+    }
+
+    /// Returns the area covered by the cells of `coords`, or `None` if there are none.
+    pub fn map_bounds<'a, I>(&self, coords: I) -> Option<Bounds>
+    where
+        I: IntoIterator<Item = &'a CellCoord>,
+    {
+        coords
+            .into_iter()
+            .flat_map(|coord| {
+                // Corners are given about the origin of the layout.
+                let offset = &self.layout.pixel_from_coord(coord) - self.layout.origin();
+                self.layout
+                    .polygon_corners(&self.layout.cell_from_coord(coord))
+                    .into_iter()
+                    .map(move |corner| &corner + &offset)
+            })
+            .fold(None, |bounds: Option<Bounds>, corner| {
+                Some(match bounds {
+                    Some(bounds) => Bounds::new(
+                        Point::new(bounds.min.x.min(corner.x), bounds.min.y.min(corner.y)),
+                        Point::new(bounds.max.x.max(corner.x), bounds.max.y.max(corner.y)),
+                    ),
+                    None => Bounds::new(corner.clone(), corner),
+                })
+            })
+    }
+
+    /// Keeps every camera inside the cells of `coords`.
+    pub fn set_map_bounds<'a, I>(&mut self, coords: I) -> Result<(), RenderError>
+    where
+        I: IntoIterator<Item = &'a CellCoord>,
+    {
+        let bounds = self.map_bounds(coords);
+        for camera in &mut self.cameras {
+            camera.set_bounds(bounds.clone());
+        }
+
+        self.update_camera()
+    }
+
+    /// Starts dragging the camera with the mouse. The world point under the mouse follows it
+    /// until `end_drag()`.
+    pub fn start_drag(&mut self, event: &MouseEvent) -> Result<(), RenderError> {
+        self.drag_anchor = Some(self.viewport.event_point(event)?);
+
+        Ok(())
+    }
+
+    /// Moves the camera so the point grabbed by `start_drag()` is under the mouse again.
+    pub fn drag(&mut self, event: &MouseEvent) -> Result<(), RenderError> {
+        if let Some(anchor) = self.drag_anchor.clone() {
+            let point = self.viewport.event_point(event)?;
+            self.pan(&(&anchor - &point))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag_anchor = None;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag_anchor.is_some()
+    }
+
+    /// Holds the world points under the fingers of a touch event, so moving the fingers pans and
+    /// pinch zooms the camera. Call this when fingers are added or lifted.
+    pub fn touch_start(&mut self, event: &TouchEvent) -> Result<(), RenderError> {
+        let touches = self.touch_points(event)?;
+        self.touches.start(&touches);
+
+        Ok(())
+    }
+
+    /// Moves the camera so the points held by `touch_start()` are under the fingers again.
+    pub fn touch_move(&mut self, event: &TouchEvent) -> Result<(), RenderError> {
+        let touches = self.touch_points(event)?;
+        if let Some(change) = self.touches.update(&touches) {
+            self.zoom_at(change.zoom, &change.center)?;
+            self.pan(&change.pan)?;
+        }
+
+        Ok(())
+    }
+
+    // Returns the fingers still touching the page and where they are in world space.
+    fn touch_points(&self, event: &TouchEvent) -> Result<Vec<(i32, Point)>, RenderError> {
+        let touch_list = event.touches();
+        let mut touches = Vec::new();
+        for i in 0..touch_list.length() {
+            if let Some(touch) = touch_list.get(i) {
+                let client = Point::new(touch.client_x() as f32, touch.client_y() as f32);
+                touches.push((touch.identifier(), self.viewport.client_point(&client)?));
+            }
+        }
+
+        Ok(touches)
+    }
+
+    /// Adds a viewport that shows what the camera `camera` sees and returns the id of the view.
+    pub fn add_view(
+        &mut self,
+        mut viewport: Viewport,
+        camera: usize,
+    ) -> Result<usize, RenderError> {
+        if camera >= self.cameras.len() {
+            return Err(RenderError::new("invalid camera id"));
+        }
+
+        viewport.look_at(&self.cameras[camera])?;
+        self.views.push(View { viewport, camera });
+
+        Ok(self.views.len() - 1)
+    }
+
+    pub fn view(&self, id: usize) -> &View {
+        &self.views[id]
+    }
+
+    pub fn view_mut(&mut self, id: usize) -> &mut View {
+        &mut self.views[id]
+    }
+
+    /// Adds a minimap that shows all of `coords` as cells filled with `color` and a frame around
+    /// what the active camera sees. Returns the id of the minimap's view.
+    pub fn add_minimap<'a, I>(
+        &mut self,
+        backend: Box<dyn Backend>,
+        width: i32,
+        height: i32,
+        coords: I,
+        color: &str,
+    ) -> Result<usize, RenderError>
+    where
+        I: IntoIterator<Item = &'a CellCoord> + Clone,
+    {
+        let mut camera = Camera::new(width, height);
+        if let Some(bounds) = self.map_bounds(coords.clone()) {
+            camera.fit(&bounds);
+        }
+
+        let mut viewport = Viewport::with_backend(backend);
+        viewport.insert_layer(0, minimap::cells_layer(&self.layout, coords, color));
+        viewport.insert_layer(
+            1,
+            minimap::frame_layer(self.camera(self.active_camera()), &camera, color),
+        );
+
+        let camera = self.add_camera(camera);
+        let view = self.add_view(viewport, camera)?;
+        self.minimap = Some(view);
+
+        self.views[view].viewport.render(&self.layout)?;
+
+        Ok(view)
+    }
+
+    /// Converts the position of a mouse event on the minimap into world space.
+    pub fn minimap_event_point(&self, event: &MouseEvent) -> Result<Point, RenderError> {
+        match self.minimap {
+            Some(view) => self.views[view].viewport.event_point(event),
+            None => Err(RenderError::new("world has no minimap")),
+        }
+    }
+
+    // Shows what each camera sees.
+    fn update_camera(&mut self) -> Result<(), RenderError> {
+        let camera = self.camera(self.active_camera()).clone();
         self.viewport.look_at(&camera)?;
+
+        for view in &mut self.views {
+            view.viewport.look_at(&self.cameras[view.camera])?;
+        }
+
+        // Keep the minimap frame around what the active camera sees.
+        if let Some(view) = self.minimap {
+            let viewport = &mut self.views[view].viewport;
+            if let Some(layer) = viewport.layer_mut(minimap::MINIMAP_FRAME_LAYER) {
+                minimap::update_frame(layer, &camera);
+            }
+            viewport.render(&self.layout)?;
+        }
+
+        Ok(())
+    }
+
+    /// Plays `animation` on the sprite at `coord` of the layer `layer_name` in the main viewport.
+    pub fn animate(&mut self, layer_name: &str, coord: CellCoord, animation: Animation) {
+        self.animator.play(layer_name, coord, animation);
+    }
+
+    pub fn stop_animation(&mut self, layer_name: &str, coord: &CellCoord) {
+        self.animator.stop(layer_name, coord);
+    }
+
+    /// Advances all animations to the time `now` in milliseconds and draws whatever changed. Call
+    /// this every frame, for example from `on_animation_frame`.
+    pub fn update_animations(&mut self, now: f64) -> Result<(), RenderError> {
+        if !self.animator.is_empty() {
+            self.animator.update(now, &mut self.viewport);
+        }
+
+        // Backends can fall behind without a change to the layers, such as when an image they
+        // draw finishes loading. Rendering an unchanged viewport does nothing.
+        self.viewport.render(&self.layout)?;
+        for view in &mut self.views {
+            view.viewport.render(&self.layout)?;
+        }
+
         Ok(())
     }
 
@@ -84,40 +383,104 @@ where
         debug("rendering world".to_owned());
 
         self.viewport.render(&self.layout)?;
+        for view in &mut self.views {
+            view.viewport.render(&self.layout)?;
+        }
 
         debug("world rendered".to_owned());
 
         Ok(())
     }
 
+    /// Exports what the active camera sees of the world as a standalone SVG document, for bug
+    /// reports and screenshots.
+    pub fn export_svg(&self) -> String {
+        self.viewport
+            .export_svg(self.camera(self.active_camera()), &self.layout)
+    }
+
     pub fn clear_layer(&mut self, layer_name: &str) {
         self.viewport
-            .layer_mut(layer_name)
-            .expect("layer does not exist")
-            .clear()
+            .clear_layer(layer_name)
             .expect("failed to clear layer on render layer");
     }
 
-    pub fn render_layer(&self, layer_name: &str) {
+    pub fn render_layer(&mut self, layer_name: &str) {
         self.viewport
-            .layer(layer_name)
-            .expect("layer does not exist")
-            .render(&self.layout)
+            .render_layer(layer_name, &self.layout)
             .expect(format!("failed to render layer: {:?}", layer_name).as_str());
     }
 
+    /// Converts the position of a mouse event on the main viewport into world space.
+    pub fn event_point(&self, event: &MouseEvent) -> Result<Point, RenderError> {
+        self.viewport.event_point(event)
+    }
+
+    /// Returns the top sprite of the main viewport covering the world point `point`.
+    pub fn hit_test(&self, point: &Point) -> Option<Hit> {
+        self.viewport.hit_test(point)
+    }
+
     pub fn event_cell(&self, event: &MouseEvent) -> Result<C, RenderError> {
         let pixel = self.viewport.event_point(event)?;
         Ok(self.layout.pixel_to_cell(&pixel))
     }
 
-    pub fn on_mouse_event<H>(&self, event: UserEvent, handler: H) -> Result<(), JsValue>
+    /// Calls `handler` with the `event`s on the page until the returned listener is dropped.
+    pub fn on_mouse_event<H>(&self, event: UserEvent, handler: H) -> Result<EventListener, JsValue>
     where
         H: 'static + FnMut(MouseEvent),
     {
-        add_mouse_event(&get_body(), &event, handler);
+        add_mouse_event(&get_body(), &event, handler)
+    }
 
-        Ok(())
+    /// Calls `handler` with the `event`s on the page until the returned listener is dropped.
+    pub fn on_key_event<H>(&self, event: UserEvent, handler: H) -> Result<EventListener, JsValue>
+    where
+        H: 'static + FnMut(KeyboardEvent),
+    {
+        add_key_event(&get_body(), &event, handler)
+    }
+
+    /// Calls `handler` with the `event`s on the page until the returned listener is dropped.
+    /// The handler may call `prevent_default()` to stop the page from scrolling.
+    pub fn on_wheel_event<H>(&self, event: UserEvent, handler: H) -> Result<EventListener, JsValue>
+    where
+        H: 'static + FnMut(WheelEvent),
+    {
+        add_wheel_event(&get_body(), &event, handler)
+    }
+
+    /// Calls `handler` with the `event`s on the page until the returned listener is dropped.
+    pub fn on_pointer_event<H>(
+        &self,
+        event: UserEvent,
+        handler: H,
+    ) -> Result<EventListener, JsValue>
+    where
+        H: 'static + FnMut(PointerEvent),
+    {
+        add_pointer_event(&get_body(), &event, handler)
+    }
+
+    /// Calls `handler` with the `event`s on the page until the returned listener is dropped.
+    /// The handler may call `prevent_default()` to stop the page from scrolling.
+    pub fn on_touch_event<H>(&self, event: UserEvent, handler: H) -> Result<EventListener, JsValue>
+    where
+        H: 'static + FnMut(TouchEvent),
+    {
+        add_touch_event(&get_body(), &event, handler)
+    }
+
+    /// Sends every event of the pointer of `event` to the world until it is released, even when
+    /// the pointer leaves the page, such as while dragging a belt past the edge of the world.
+    pub fn capture_pointer(&self, event: &PointerEvent) -> Result<(), JsValue> {
+        get_body().set_pointer_capture(event.pointer_id())
+    }
+
+    /// Stops capturing the pointer of `event`. Pointers are released anyway when lifted.
+    pub fn release_pointer(&self, event: &PointerEvent) -> Result<(), JsValue> {
+        get_body().release_pointer_capture(event.pointer_id())
     }
 }
 
@@ -153,20 +516,68 @@ impl<C: Cell> PartialEq for CellPriority<C> {
     }
 }
 
+// Extra cost of passing underground so that surface routes are preferred.
+const JUMP_COST: i32 = 2;
+
+/// Finds the shortest path between two cells that avoids collisions. A path may
+/// jump in a straight line beneath collisions to a cell at most `max_span` cells
+/// away; consecutive cells in the returned path that are not neighbors mark
+/// such a jump. A `max_span` below 2 disables jumping.
 pub fn shortest_path<C>(
     from: &C,
     to: &C,
     world: &HashSet<CellCoord>,
     collisions: &Collisions,
+    max_span: i32,
 ) -> Option<Vec<C>>
 where
     C: Cell,
 {
-    a_star_search(from, to, world, collisions)
+    a_star_search(from, to, world, collisions, max_span)
 }
 
+// Distance between the cells, which never overestimates the cost of a path so the cheapest path
+// is found even when jumping gets closer sooner.
 fn heuristic(a: &CellCoord, b: &CellCoord) -> i32 {
-    (a.x - b.x).abs() + (a.y - b.y).abs() + (a.z - b.z).abs()
+    ((a.x - b.x).abs() + (a.y - b.y).abs() + (a.z - b.z).abs()) / 2
+}
+
+// Returns the cells reachable from `cell` with the cost of traversing to each.
+fn edges<C: Cell>(
+    cell: &C,
+    base_map: &HashSet<CellCoord>,
+    collisions: &HashSet<CellCoord>,
+    max_span: i32,
+) -> Vec<(C, i32)> {
+    let mut edges: Vec<(C, i32)> = Vec::new();
+
+    for direction in cell.directions() {
+        let next = cell.neighbor(*direction);
+        if !base_map.contains(&next.coord()) {
+            continue;
+        }
+
+        if !collisions.contains(&next.coord()) {
+            edges.push((next, 1));
+            continue;
+        }
+
+        // Jump beneath the collision to the first free cell within the span.
+        let mut jump = next;
+        for span in 2..=max_span {
+            jump = jump.neighbor(*direction);
+            if !base_map.contains(&jump.coord()) {
+                break;
+            }
+
+            if !collisions.contains(&jump.coord()) {
+                edges.push((jump, span + JUMP_COST));
+                break;
+            }
+        }
+    }
+
+    edges
 }
 
 fn a_star_search<C: Cell>(
@@ -174,6 +585,7 @@ fn a_star_search<C: Cell>(
     end: &C,
     base_map: &HashSet<CellCoord>,
     collisions: &HashSet<CellCoord>,
+    max_span: i32,
 ) -> Option<Vec<C>> {
     debug(format!("=============================="));
     let mut frontier: BinaryHeap<Reverse<CellPriority<C>>> = BinaryHeap::new();
@@ -220,15 +632,11 @@ fn a_star_search<C: Cell>(
                 return Some(path);
             }
 
-            for next in &current.0.cell.neighbors() {
-                if !base_map.contains(&next.coord()) || collisions.contains(&next.coord()) {
-                    continue;
-                }
+            for (next, cost_to_next) in &edges(&current.0.cell, base_map, collisions, max_span) {
                 let current_cost = cost_so_far
                     .get(&current.0.cell.coord())
                     .or(Some(&0))
                     .expect("failed to get cost so far");
-                let cost_to_next = 1; // This is the cost of traversing to this next cell.
                 let new_cost = current_cost + cost_to_next;
                 let next_cost = *cost_so_far
                     .get(&next.coord())
@@ -274,3 +682,78 @@ def a_star_search(graph, start, goal):
                 came_from[next] = current
     return came_from, cost_so_far
 */
+
+#[cfg(test)]
+mod test {
+    use super::super::cell::Hex;
+    use super::*;
+
+    fn hex(x: i32, y: i32) -> Hex {
+        Hex::new(x as f32, y as f32, (-x - y) as f32)
+    }
+
+    // A single row of cells, so paths cannot go around collisions.
+    fn row(length: i32) -> HashSet<CellCoord> {
+        (0..length).map(|x| hex(x, -x).coord()).collect()
+    }
+
+    fn path(
+        from: &Hex,
+        to: &Hex,
+        world: &HashSet<CellCoord>,
+        collisions: &Collisions,
+        max_span: i32,
+    ) -> Option<Vec<CellCoord>> {
+        let path = shortest_path(from, to, world, collisions, max_span)?;
+        Some(path.iter().map(|cell| cell.coord()).collect())
+    }
+
+    #[test]
+    fn shortest_path_jump_01() {
+        let world = row(5);
+        let collisions = [hex(2, -2).coord()].iter().cloned().collect();
+
+        // The jump lands on the first free cell past the collision.
+        assert_eq!(
+            path(&hex(0, 0), &hex(4, -4), &world, &collisions, 5),
+            Some(vec![
+                hex(0, 0).coord(),
+                hex(1, -1).coord(),
+                hex(3, -3).coord(),
+                hex(4, -4).coord(),
+            ])
+        );
+    }
+
+    #[test]
+    fn shortest_path_span_01() {
+        let world = row(5);
+        let collisions = [hex(2, -2).coord(), hex(3, -3).coord()]
+            .iter()
+            .cloned()
+            .collect();
+
+        // Jumping from the first cell to the fourth spans three cells.
+        assert_eq!(path(&hex(0, 0), &hex(4, -4), &world, &collisions, 2), None);
+        assert_eq!(
+            path(&hex(1, -1), &hex(4, -4), &world, &collisions, 3),
+            Some(vec![hex(1, -1).coord(), hex(4, -4).coord()])
+        );
+    }
+
+    #[test]
+    fn shortest_path_cost_01() {
+        let world: HashSet<CellCoord> = (-2..=2)
+            .flat_map(|x: i32| (-2..=2).map(move |y| (x, y)))
+            .filter(|(x, y)| (x + y).abs() <= 2)
+            .map(|(x, y)| hex(x, y).coord())
+            .collect();
+        let collisions = [hex(0, 0).coord()].iter().cloned().collect();
+
+        // Walking around the collision takes three cells, which is cheaper than jumping it.
+        let walked =
+            path(&hex(-1, 1), &hex(1, -1), &world, &collisions, 5).expect("failed to find a path");
+        assert_eq!(walked.len(), 4);
+        assert!(!walked.contains(&hex(0, 0).coord()));
+    }
+}
@@ -8,13 +8,16 @@ use ron::ser::{to_string_pretty, PrettyConfig};
 use serde::Serialize;
 use std::iter::FromIterator;
 
+use super::models::{Inserter, Stats};
 use super::view::{
-    Belt, Building, BuildingState, GameState, Material, UserAction, GAME_STATE, WORLD,
+    Belt, Building, BuildingState, GameState, InputAction, Material, UserAction, EVENTS,
+    GAME_STATE, INPUT, UNDERGROUND_MAX_SPAN, WORLD,
 };
 use crate::engine::{
-    alert_js, debug, get_target, rng, shortest_path, Cell, CellCoord, Hex, HexLayout,
-    HexOrientation, Layer, Layout, Point, Rectangle, Renderable, Shape, Sprite, Texture,
-    TextureBorder, UserEvent,
+    add_pointer_event, alert_js, debug, get_target, info, on_animation_frame, rng, shortest_path,
+    Animation, CanvasBackend, Cell, CellCoord, Easing, Hex, HexLayout, HexOrientation, Hit, Input,
+    Keyframe, Layer, Layout, Point, Rectangle, RenderError, Shape, Sprite, SpriteEvent, Stroke,
+    Texture, TextureAtlas, TextureBorder, UserEvent, World, TICKS_PER_SECOND, ZOOM_STEP,
 };
 
 use wasm_bindgen::prelude::*;
@@ -22,6 +25,36 @@ use wasm_bindgen::*;
 
 use web_sys::{Document, Element, Event, HtmlElement, MouseEvent};
 
+// Width and height in pixels of an inserter, and its color.
+const INSERTER_SIZE: i32 = 7;
+const INSERTER_COLOR: &str = "gray";
+// Number of ticks an inserter takes to swing from one side to the other.
+const INSERTER_SWING_TICKS: usize = TICKS_PER_SECOND / 2;
+// Most ticks run in one frame when the simulation falls behind.
+const MAX_TICKS_PER_FRAME: usize = 10;
+// Distance in screen pixels the camera pans each second while a key is held.
+const KEY_PAN_SPEED: f32 = 600.0;
+const MINIMAP_WIDTH: i32 = 200;
+const MINIMAP_HEIGHT: i32 = 175;
+// Size in pixels of the text labelling buildings, and its distance below the building.
+const LABEL_FONT_SIZE: i32 = 6;
+const LABEL_OFFSET: f32 = 12.0;
+// Opacity of a building while the mouse is over it.
+const BUILDING_HOVER_OPACITY: f32 = 0.7;
+const TEXTURE_ATLAS: &str = include_str!("../../../game/textures/atlas.ron");
+// Width in pixels of the line previewing a belt.
+const BELT_PREVIEW_WIDTH: i32 = 3;
+// Degrees a building turns to face the next side of its cell.
+const BUILDING_ROTATION_STEP: f32 = 60.0;
+// Width and height in pixels of a building.
+const BUILDING_SIZE: i32 = 15;
+// Outline of the cell under the mouse.
+const CURSOR_COLOR: &str = "white";
+const CURSOR_BORDER_WIDTH: i32 = 2;
+// Opacity of the building shown where it would be placed, and its color where it cannot be.
+const GHOST_OPACITY: f32 = 0.5;
+const BLOCKED_GHOST_COLOR: &str = "red";
+
 pub fn run() -> Result<(), JsValue> {
     //alert(format!("target_id: {}", target_id).as_str());
     /*
@@ -41,12 +74,20 @@ pub fn run() -> Result<(), JsValue> {
 
         let mut cell_ids: Vec<String> = Vec::new();
         let mut cell_coords: Vec<CellCoord> = Vec::new();
-        let mut building_sprite_ids: Vec<String> = Vec::new();
 
         let mut bg_layer = Layer::new("background");
         let mut building_layer = Layer::new("buildings");
         let mut belt_layer = Layer::new("belts");
-        let belt_preview_layer = Layer::new("belt_preview");
+        let mut belt_preview_layer = Layer::new("belt_preview");
+        let mut label_layer = Layer::new("labels");
+        let mut cursor_layer = Layer::new("cursor");
+        let mut ghost_layer = Layer::new("ghost");
+        // Overlays do not take mouse events from the sprites under them.
+        belt_preview_layer.set_interactive(false);
+        label_layer.set_interactive(false);
+        cursor_layer.set_interactive(false);
+        ghost_layer.set_interactive(false);
+        ghost_layer.set_opacity(GHOST_OPACITY);
 
         // Background
         {
@@ -67,7 +108,7 @@ pub fn run() -> Result<(), JsValue> {
                     let sprite = Sprite::new(&sprite_id, &cell_shape, &position, &texture);
                     cell_ids.push(sprite.id().to_owned());
                     cell_coords.push(cell.coord());
-                    bg_layer.sprites.insert(cell.coord(), sprite);
+                    bg_layer.add_sprite(cell.coord(), sprite);
                 }
             });
         }
@@ -75,27 +116,18 @@ pub fn run() -> Result<(), JsValue> {
         // Buildings
         {
             let building_shape = Shape::Rectangle {
-                width: 15,
-                height: 15,
+                width: BUILDING_SIZE,
+                height: BUILDING_SIZE,
             };
 
             let cell = Cell::new(-1.0, -1.0, 2.0);
             let position = w.borrow().layout.cell_to_pixel(&cell);
 
-            let texture = {
-                let mut tex = Texture::new();
-                //let tex_border = TextureBorder::new(1, "black");
-                //tex.border = Some(tex_border);
-                //tex.background_color = Some("brown".to_owned());
-                tex.image = Some("game/textures/factory.svg".to_owned());
-                tex
-            };
+            let texture = Texture::named("factory");
             let sprite_id = rng::uid().to_string();
             let building_sprite = Sprite::new(&sprite_id, &building_shape, &position, &texture);
 
-            building_sprite_ids.push(sprite_id.to_owned());
-
-            building_layer.sprites.insert(cell.coord(), building_sprite);
+            building_layer.add_sprite(cell.coord(), building_sprite);
 
             GAME_STATE.with(|game_state| {
                 game_state.borrow_mut().add_building(Building::new(
@@ -105,29 +137,79 @@ pub fn run() -> Result<(), JsValue> {
                 ));
             });
         }
+        // Building labels
+        {
+            GAME_STATE.with(|game_state| {
+                for building in game_state.borrow().buildings.values() {
+                    let state = String::from(&building.state);
+                    let tooltip = building_tooltip(building);
+
+                    // Show the state under the building.
+                    let mut position = w.borrow().layout.pixel_from_coord(&building.coord);
+                    position.y += LABEL_OFFSET;
+
+                    let label_shape = Shape::text(&state, "sans-serif", LABEL_FONT_SIZE);
+                    let sprite_id = rng::uid().to_string();
+                    let mut label_sprite =
+                        Sprite::new(&sprite_id, &label_shape, &position, &Texture::new());
+                    label_sprite.set_tooltip(Some(&tooltip));
+                    label_layer.add_sprite(building.coord, label_sprite);
+
+                    if let Some(building_sprite) = building_layer.sprite_mut(&building.coord) {
+                        building_sprite.set_tooltip(Some(&tooltip));
+                    }
+                }
+            });
+        }
         // Belts
         {
-            let belt_shape = Shape::Cell;
+            let path: [Hex; 2] = [Cell::new(-4.0, -1.0, 5.0), Cell::new(-3.0, -1.0, 4.0)];
 
-            let cell = Cell::new(-2.0, -1.0, 3.0);
-            let position = w.borrow().layout.cell_to_pixel(&cell);
+            for cell in &path {
+                let position = w.borrow().layout.cell_to_pixel(cell);
+                belt_layer.add_sprite(cell.coord(), belt_sprite(&position));
+            }
+
+            let mut belt = Belt::from_path(&path);
+            belt.items
+                .put(Material::Mat1)
+                .expect("new belt should have room");
 
-            let texture = {
-                let mut tex = Texture::new();
-                let tex_border = TextureBorder::new(1, "black");
-                tex.border = Some(tex_border);
-                tex.background_color = Some("gray".to_owned());
-                tex
+            GAME_STATE.with(|game_state| {
+                game_state.borrow_mut().add_belt(belt);
+            });
+        }
+        // Inserters
+        {
+            // Picks from the end of the belt and drops into the building.
+            let cell: Hex = Cell::new(-2.0, -1.0, 3.0);
+            let belt_end = CellCoord::new(-3, -1, 4);
+            let direction = *cell
+                .directions()
+                .iter()
+                .find(|direction| cell.neighbor(**direction).coord() == belt_end)
+                .expect("inserter should be next to the belt");
+
+            let inserter_shape = Shape::Rectangle {
+                width: INSERTER_SIZE,
+                height: INSERTER_SIZE,
             };
-            let sprite_id = rng::uid().to_string();
-            let belt_sprite = Sprite::new(&sprite_id, &belt_shape, &position, &texture);
+            let position = w.borrow().layout.cell_to_pixel(&cell);
+            let mut texture = Texture::new();
+            texture.background_color = Some(INSERTER_COLOR.to_owned());
+            let inserter_sprite = Sprite::new(
+                &rng::uid().to_string(),
+                &inserter_shape,
+                &position,
+                &texture,
+            );
 
-            belt_layer.sprites.insert(cell.coord(), belt_sprite);
+            building_layer.add_sprite(cell.coord(), inserter_sprite);
 
             GAME_STATE.with(|game_state| {
-                let mut contents: HashMap<CellCoord, Option<Material>> = HashMap::new();
-                contents.insert(cell.coord().clone(), None);
-                game_state.borrow_mut().add_belt(Belt::new(contents));
+                game_state
+                    .borrow_mut()
+                    .add_inserter(cell.coord(), Inserter::new(direction, INSERTER_SWING_TICKS));
             });
         }
 
@@ -157,20 +239,135 @@ pub fn run() -> Result<(), JsValue> {
             */
         }
 
+        // Share the textures used by many sprites.
+        let atlas = TextureAtlas::from_ron(TEXTURE_ATLAS)?;
+        w.borrow_mut().viewport.set_atlas(atlas)?;
+
         w.borrow_mut().viewport.insert_layer(0, bg_layer);
         w.borrow_mut().viewport.insert_layer(1, building_layer);
         w.borrow_mut().viewport.insert_layer(2, belt_layer);
         w.borrow_mut().viewport.insert_layer(3, belt_preview_layer);
+        w.borrow_mut().viewport.insert_layer(4, label_layer);
+        w.borrow_mut().viewport.insert_layer(5, cursor_layer);
+        w.borrow_mut().viewport.insert_layer(6, ghost_layer);
 
         w.borrow_mut().render()?;
 
-        // Attach an event to a building.
-        for building_sprite_id in &building_sprite_ids {
-            let id = building_sprite_id.to_owned();
-            Sprite::on(&building_sprite_id, UserEvent::MouseClick, move |_| {
-                //alert_js(format!("You clicked building {:?}", id));
-            })?;
-        }
+        // Buildings pulse while they are working.
+        GAME_STATE.with(|game_state| {
+            for building in game_state.borrow().buildings.values() {
+                if let BuildingState::Working = building.state {
+                    w.borrow_mut()
+                        .animate("buildings", building.coord, working_animation());
+                }
+            }
+        });
+
+        // Drive animations and the simulation from the browser's frame loop.
+        let mut last_frame: Option<f64> = None;
+        let mut unticked = 0.0;
+        on_animation_frame(move |time| {
+            let delta = time - last_frame.unwrap_or(time);
+            last_frame = Some(time);
+
+            // Run the ticks that fit in the time since the last frame. Time that cannot be caught
+            // up, such as while the page was hidden, is dropped.
+            unticked += delta * TICKS_PER_SECOND as f64 / 1000.0;
+            let ticks = cmp::min(unticked as usize, MAX_TICKS_PER_FRAME);
+            unticked = unticked.fract();
+            GAME_STATE.with(|game_state| {
+                for _ in 0..ticks {
+                    game_state.borrow_mut().tick();
+                }
+            });
+
+            WORLD.with(|w| {
+                if ticks > 0 {
+                    update_labels(&mut w.borrow_mut());
+                }
+                pan_held(&mut w.borrow_mut(), delta).expect("failed to pan camera");
+                w.borrow_mut()
+                    .update_animations(time)
+                    .expect("failed to update animations");
+            })
+        });
+
+        // Keep the camera over the map.
+        GAME_STATE.with(|game_state| w.borrow_mut().set_map_bounds(&game_state.borrow().world))?;
+
+        // Show an overview of the whole map. Clicking it moves the camera there. It is drawn on a
+        // canvas, which stays fast however many cells the map has.
+        GAME_STATE.with(|game_state| -> Result<(), JsValue> {
+            let backend = CanvasBackend::new("minimap", MINIMAP_WIDTH, MINIMAP_HEIGHT)?;
+            w.borrow_mut().add_minimap(
+                Box::new(backend),
+                MINIMAP_WIDTH,
+                MINIMAP_HEIGHT,
+                &game_state.borrow().world,
+                "lightgray",
+            )?;
+
+            Ok(())
+        })?;
+
+        add_pointer_event(
+            &get_target("minimap")?,
+            &UserEvent::PointerDown,
+            |event: web_sys::PointerEvent| {
+                // Do not let the world start placing a belt.
+                event.stop_propagation();
+
+                WORLD.with(|w| {
+                    let point = w
+                        .borrow()
+                        .minimap_event_point(&event)
+                        .expect("failed to get minimap point");
+                    w.borrow_mut()
+                        .look_at(&point)
+                        .expect("failed to look at minimap point");
+                })
+            },
+        )?
+        // The game's listeners are kept for as long as the page is open.
+        .forget();
+
+        // Attach an event to the buildings. Hovering one fades it.
+        EVENTS.with(|events| {
+            events
+                .borrow_mut()
+                .on_layer("buildings", |hit: &Hit, event: &SpriteEvent| {
+                    let opacity = match event {
+                        SpriteEvent::Click { .. } => {
+                            let held = GAME_STATE.with(|game_state| {
+                                let game_state = game_state.borrow();
+                                game_state
+                                    .building_at(&hit.coord)
+                                    .map(|building| building.buffer.count())
+                            });
+                            debug(format!(
+                                "clicked building at {:?} holding {:?}",
+                                hit.coord, held
+                            ));
+                            return;
+                        }
+                        SpriteEvent::HoverEnter => BUILDING_HOVER_OPACITY,
+                        SpriteEvent::HoverLeave => 1.0,
+                        _ => return,
+                    };
+
+                    WORLD.with(|w| {
+                        let mut world = w.borrow_mut();
+                        if let Some(building) = world
+                            .viewport
+                            .layer_mut(&hit.layer)
+                            .and_then(|layer| layer.sprite_mut(&hit.coord))
+                        {
+                            building.set_opacity(opacity);
+                        }
+                        world.render().expect("failed to render building hover");
+                    });
+                });
+        });
 
         // Attach event to the world to find the cell that was clicked.
         w.borrow()
@@ -184,11 +381,46 @@ pub fn run() -> Result<(), JsValue> {
                         Ok(())
                     })
                     .expect("mouse event failed");
-            })?;
+            })?
+            .forget();
 
+        // Pointers are captured so belts and the camera can be dragged past the edge of the world.
         w.borrow()
-            .on_mouse_event(UserEvent::MouseDown, |event: web_sys::MouseEvent| {
+            .on_pointer_event(UserEvent::PointerDown, |event: web_sys::PointerEvent| {
+                // Touches pan and zoom instead.
+                if event.pointer_type() == "touch" {
+                    return;
+                }
+
                 WORLD.with(|w| {
+                    let action = Input::from_mouse_event(&event).and_then(|input| {
+                        INPUT.with(|bindings| bindings.borrow().action(&input).copied())
+                    });
+
+                    if action == Some(InputAction::DragCamera) {
+                        w.borrow_mut()
+                            .start_drag(&event)
+                            .expect("failed to start camera drag");
+                        w.borrow()
+                            .capture_pointer(&event)
+                            .expect("failed to capture pointer");
+                        return;
+                    }
+
+                    dispatch_mouse_event(&event, UserEvent::MouseDown);
+
+                    match action {
+                        Some(InputAction::Place) => w
+                            .borrow()
+                            .capture_pointer(&event)
+                            .expect("failed to capture pointer"),
+                        Some(InputAction::CancelPlacement) => {
+                            cancel_placement(&mut w.borrow_mut());
+                            return;
+                        }
+                        _ => return,
+                    }
+
                     let cell = w
                         .borrow()
                         .event_cell(&event)
@@ -214,15 +446,24 @@ pub fn run() -> Result<(), JsValue> {
                         };
                     });
                 })
-            })?;
+            })?
+            .forget();
 
         w.borrow()
             .on_mouse_event(UserEvent::MouseMove, |event: web_sys::MouseEvent| {
                 WORLD.with(|w| {
+                    if w.borrow().is_dragging() {
+                        w.borrow_mut().drag(&event).expect("failed to drag camera");
+                        return;
+                    }
+
+                    dispatch_mouse_event(&event, UserEvent::MouseMove);
+
                     let cell = w
                         .borrow()
                         .event_cell(&event)
                         .expect("failed to get event cell");
+                    hover_cell(&mut w.borrow_mut(), cell.coord()).expect("failed to move cursor");
 
                     // FIXME: Need to check if starting from building node. Otherwise, every mouse down will start placing a belt.
                     GAME_STATE.with(|game_state| {
@@ -244,6 +485,7 @@ pub fn run() -> Result<(), JsValue> {
                                     &current_end,
                                     &game_state.borrow().world,
                                     &game_state.borrow().collisions(),
+                                    UNDERGROUND_MAX_SPAN,
                                 );
 
                                 if let Some(p) = path {
@@ -275,15 +517,22 @@ pub fn run() -> Result<(), JsValue> {
                         }
                     });
                 })
-            })?;
+            })?
+            .forget();
 
         w.borrow()
             .on_mouse_event(UserEvent::MouseUp, |event: web_sys::MouseEvent| {
                 WORLD.with(|w| {
+                    if w.borrow().is_dragging() {
+                        w.borrow_mut().end_drag();
+                        return;
+                    }
+
+                    dispatch_mouse_event(&event, UserEvent::MouseUp);
+
                     //let cell = w.borrow().event_cell(&event);
 
                     GAME_STATE.with(|game_state| {
-                        // TODO: Place belt along the shortest path.
                         let belt_preview = match game_state.borrow().current_action {
                             Some(UserAction::PlacingBelt {
                                 begin,
@@ -305,11 +554,19 @@ pub fn run() -> Result<(), JsValue> {
                                     &end_cell,
                                     &game_state.borrow().world,
                                     &game_state.borrow().collisions(),
+                                    UNDERGROUND_MAX_SPAN,
                                 );
 
+                                // The path may start or end on something already placed.
+                                let path = path.filter(|p| {
+                                    let game_state = game_state.borrow();
+                                    p.iter().all(|cell| game_state.can_place_at(&cell.coord()))
+                                });
+
                                 if let Some(p) = path {
-                                    // TODO: Draw final belt.
-                                    debug(format!("drawing belt {:?}", p));
+                                    debug(format!("placing belt {:?}", p));
+                                    game_state.borrow_mut().add_belt(Belt::from_path(&p));
+                                    add_belt_sprites(&mut w.borrow_mut(), &p);
 
                                     // Look at the end of the belt.
                                     let end_position = w
@@ -335,7 +592,133 @@ pub fn run() -> Result<(), JsValue> {
                             .expect("failed to clear belt_preview");
                     });
                 })
-            })?;
+            })?
+            .forget();
+
+        // Scroll to zoom about the mouse.
+        w.borrow()
+            .on_wheel_event(UserEvent::Wheel, |event: web_sys::WheelEvent| {
+                // Do not scroll the page.
+                event.prevent_default();
+                if event.delta_y() == 0.0 {
+                    return;
+                }
+
+                let factor = if event.delta_y() < 0.0 {
+                    ZOOM_STEP
+                } else {
+                    1.0 / ZOOM_STEP
+                };
+                WORLD.with(|w| {
+                    let point = w
+                        .borrow()
+                        .event_point(&event)
+                        .expect("failed to get wheel point");
+                    w.borrow_mut()
+                        .zoom_at(factor, &point)
+                        .expect("failed to zoom");
+                })
+            })?
+            .forget();
+
+        // The right button is bound to an action, so do not open the browser's menu.
+        w.borrow()
+            .on_mouse_event(UserEvent::ContextMenu, |event: web_sys::MouseEvent| {
+                event.prevent_default();
+            })?
+            .forget();
+
+        // Drag one finger to pan and pinch two to zoom. Fingers are held again whenever one is
+        // added or lifted.
+        for touch_event in [
+            UserEvent::TouchStart,
+            UserEvent::TouchEnd,
+            UserEvent::TouchCancel,
+        ] {
+            w.borrow()
+                .on_touch_event(touch_event, |event: web_sys::TouchEvent| {
+                    WORLD.with(|w| {
+                        w.borrow_mut()
+                            .touch_start(&event)
+                            .expect("failed to hold touches")
+                    })
+                })?
+                .forget();
+        }
+
+        w.borrow()
+            .on_touch_event(UserEvent::TouchMove, |event: web_sys::TouchEvent| {
+                // Do not scroll the page.
+                event.prevent_default();
+                WORLD.with(|w| {
+                    w.borrow_mut()
+                        .touch_move(&event)
+                        .expect("failed to follow touches")
+                })
+            })?
+            .forget();
+
+        // Keys are bound to actions by the input bindings.
+        w.borrow()
+            .on_key_event(UserEvent::KeyDown, |event: web_sys::KeyboardEvent| {
+                let input = Input::from_key_event(&event);
+                let action = INPUT.with(|bindings| bindings.borrow().action(&input).copied());
+
+                WORLD.with(|w| {
+                    let result = match action {
+                        // The camera pans from the frame loop while the key is held.
+                        Some(InputAction::PanLeft)
+                        | Some(InputAction::PanRight)
+                        | Some(InputAction::PanUp)
+                        | Some(InputAction::PanDown) => {
+                            GAME_STATE
+                                .with(|game_state| game_state.borrow_mut().held_keys.insert(input));
+                            Ok(())
+                        }
+                        Some(InputAction::ZoomIn) => w.borrow_mut().zoom(ZOOM_STEP),
+                        Some(InputAction::ZoomOut) => w.borrow_mut().zoom(1.0 / ZOOM_STEP),
+                        // Show or hide the building labels.
+                        Some(InputAction::ToggleLabels) => {
+                            let mut world = w.borrow_mut();
+                            if let Some(labels) = world.viewport.layer_mut("labels") {
+                                let visible = labels.is_visible();
+                                labels.set_visible(!visible);
+                            }
+                            world.render()
+                        }
+                        Some(InputAction::SelectBuilding) => {
+                            GAME_STATE.with(|game_state| {
+                                let selected = &mut game_state.borrow_mut().selected_building;
+                                *selected = match selected {
+                                    Some(_) => None,
+                                    None => Some("factory".to_owned()),
+                                };
+                            });
+                            show_cursor(&mut w.borrow_mut())
+                        }
+                        Some(InputAction::CancelPlacement) => {
+                            cancel_placement(&mut w.borrow_mut());
+                            show_cursor(&mut w.borrow_mut())
+                        }
+                        Some(InputAction::Rotate) => rotate_hovered_building(&mut w.borrow_mut()),
+                        Some(InputAction::Delete) => delete_hovered(&mut w.borrow_mut()),
+                        Some(InputAction::ExportSvg) => {
+                            info(w.borrow().export_svg());
+                            Ok(())
+                        }
+                        _ => Ok(()),
+                    };
+                    result.expect("failed to handle key");
+                })
+            })?
+            .forget();
+
+        w.borrow()
+            .on_key_event(UserEvent::KeyUp, |event: web_sys::KeyboardEvent| {
+                let input = Input::from_key_event(&event);
+                GAME_STATE.with(|game_state| game_state.borrow_mut().held_keys.remove(&input));
+            })?
+            .forget();
 
         /*
                 // Events
@@ -354,7 +737,7 @@ pub fn run() -> Result<(), JsValue> {
                         let sprite_id = (rng.rand_range(1,1000000000) as f32 + position.x + position.y).to_string();
                         let event_sprite = Sprite::new(sprite_id, &event_shape, &position, &Texture::new());
 
-                        events_layer.sprites.insert(cell.coord(), event_sprite.clone());
+                        events_layer.add_sprite(cell.coord(), event_sprite.clone());
 
                         event_sprites.push(event_sprite);
                     }
@@ -382,6 +765,295 @@ pub fn run() -> Result<(), JsValue> {
     Ok(())
 }
 
+// Sends a mouse event on the world to the sprite under the mouse.
+fn dispatch_mouse_event(event: &MouseEvent, user_event: UserEvent) {
+    // Find the sprite first so handlers are free to borrow the world.
+    let (point, hit) = WORLD.with(|w| {
+        let world = w.borrow();
+        let point = world.event_point(event).expect("failed to get event point");
+        let hit = world.hit_test(&point);
+        (point, hit)
+    });
+
+    EVENTS.with(|events| {
+        let mut events = events.borrow_mut();
+        match user_event {
+            UserEvent::MouseDown => events.pointer_down(point, hit),
+            UserEvent::MouseMove => events.pointer_move(point, hit),
+            UserEvent::MouseUp => events.pointer_up(point, hit),
+            _ => {}
+        }
+    });
+}
+
+// Pans the camera for the keys held during the `delta` milliseconds since the last frame.
+fn pan_held(world: &mut World<Hex, HexLayout>, delta: f64) -> Result<(), RenderError> {
+    let mut direction = Point::origin();
+    GAME_STATE.with(|game_state| {
+        for input in &game_state.borrow().held_keys {
+            let action = INPUT.with(|bindings| bindings.borrow().action(input).copied());
+            match action {
+                Some(InputAction::PanLeft) => direction.x -= 1.0,
+                Some(InputAction::PanRight) => direction.x += 1.0,
+                Some(InputAction::PanUp) => direction.y -= 1.0,
+                Some(InputAction::PanDown) => direction.y += 1.0,
+                _ => {}
+            }
+        }
+    });
+
+    if direction == Point::origin() {
+        return Ok(());
+    }
+
+    // Pan the same distance on screen at every zoom.
+    let zoom = world.camera(world.active_camera()).zoom();
+    let distance = KEY_PAN_SPEED * (delta / 1000.0) as f32 / zoom;
+
+    world.pan(&Point::new(direction.x * distance, direction.y * distance))
+}
+
+// Stops placing a belt or building without placing it.
+fn cancel_placement(world: &mut World<Hex, HexLayout>) {
+    GAME_STATE.with(|game_state| {
+        let mut game_state = game_state.borrow_mut();
+        game_state.current_action = None;
+        game_state.selected_building = None;
+    });
+    world.clear_layer("belt_preview");
+}
+
+// Moves the cursor to the cell `coord` under the mouse. Nothing is drawn while the mouse stays
+// in the same cell.
+fn hover_cell(world: &mut World<Hex, HexLayout>, coord: CellCoord) -> Result<(), RenderError> {
+    let changed = GAME_STATE.with(|game_state| {
+        let mut game_state = game_state.borrow_mut();
+        let hovered = Some(coord).filter(|coord| game_state.world.contains(coord));
+        let changed = game_state.hovered_cell != hovered;
+        game_state.hovered_cell = hovered;
+        changed
+    });
+
+    if changed {
+        show_cursor(world)?;
+    }
+
+    Ok(())
+}
+
+// Outlines the hovered cell and shows the selected building over it, in red if it cannot be
+// placed there.
+fn show_cursor(world: &mut World<Hex, HexLayout>) -> Result<(), RenderError> {
+    world.clear_layer("cursor");
+    world.clear_layer("ghost");
+
+    let (coord, selected, can_place) = GAME_STATE.with(|game_state| {
+        let game_state = game_state.borrow();
+        let coord = game_state.hovered_cell;
+        let can_place = coord.is_some_and(|coord| game_state.can_place_at(&coord));
+        (coord, game_state.selected_building.clone(), can_place)
+    });
+    let coord = match coord {
+        Some(coord) => coord,
+        None => return Ok(()),
+    };
+    let position = world.layout.pixel_from_coord(&coord);
+
+    let mut cursor_texture = Texture::new();
+    cursor_texture.border = Some(TextureBorder::new(CURSOR_BORDER_WIDTH, CURSOR_COLOR));
+    let cursor_sprite = Sprite::new(
+        &rng::uid().to_string(),
+        &Shape::Cell,
+        &position,
+        &cursor_texture,
+    );
+    if let Some(layer) = world.viewport.layer_mut("cursor") {
+        layer.add_sprite(coord, cursor_sprite);
+    }
+
+    if let Some(texture_name) = selected {
+        let ghost_texture = if can_place {
+            Texture::named(&texture_name)
+        } else {
+            let mut texture = Texture::new();
+            texture.background_color = Some(BLOCKED_GHOST_COLOR.to_owned());
+            texture
+        };
+        let ghost_shape = Shape::Rectangle {
+            width: BUILDING_SIZE,
+            height: BUILDING_SIZE,
+        };
+        let ghost_sprite = Sprite::new(
+            &rng::uid().to_string(),
+            &ghost_shape,
+            &position,
+            &ghost_texture,
+        );
+        if let Some(layer) = world.viewport.layer_mut("ghost") {
+            layer.add_sprite(coord, ghost_sprite);
+        }
+    }
+
+    world.render()
+}
+
+// Turns the building under the mouse to face the next side of its cell.
+fn rotate_hovered_building(world: &mut World<Hex, HexLayout>) -> Result<(), RenderError> {
+    let hit = match EVENTS.with(|events| events.borrow().hovered().cloned()) {
+        Some(hit) if hit.layer == "buildings" => hit,
+        _ => return Ok(()),
+    };
+
+    if let Some(building) = world
+        .viewport
+        .layer_mut(&hit.layer)
+        .and_then(|layer| layer.sprite_mut(&hit.coord))
+    {
+        let rotation = (building.rotation() + BUILDING_ROTATION_STEP) % 360.0;
+        building.set_rotation(rotation);
+    }
+
+    world.render()
+}
+
+// Removes the building or belts under the mouse from the game and the world.
+fn delete_hovered(world: &mut World<Hex, HexLayout>) -> Result<(), RenderError> {
+    let hit = match EVENTS.with(|events| events.borrow().hovered().cloned()) {
+        Some(hit) => hit,
+        None => return Ok(()),
+    };
+
+    // Sprites to remove, by layer and coord.
+    let removed: Vec<(&str, CellCoord)> = GAME_STATE.with(|game_state| {
+        let mut game_state = game_state.borrow_mut();
+        match hit.layer.as_str() {
+            // Inserters are shown with the buildings.
+            "buildings" => {
+                game_state.remove_building(&hit.coord);
+                game_state.remove_inserter(&hit.coord);
+                vec![("buildings", hit.coord), ("labels", hit.coord)]
+            }
+            "belts" => {
+                let ids: Vec<u32> = game_state
+                    .belts_at(&hit.coord)
+                    .iter()
+                    .map(|belt| belt.id)
+                    .collect();
+
+                let mut removed = Vec::new();
+                for id in ids {
+                    if let Some(belt) = game_state.remove_belt(id) {
+                        removed.extend(belt.path.iter().map(|coord| ("belts", *coord)));
+                    }
+                }
+                removed
+            }
+            _ => Vec::new(),
+        }
+    });
+
+    for (layer_name, coord) in removed {
+        if let Some(layer) = world.viewport.layer_mut(layer_name) {
+            if let Some(sprite_id) = layer.sprite(&coord).map(|sprite| sprite.id().to_owned()) {
+                layer.remove_sprite(sprite_id);
+            }
+        }
+    }
+
+    // The ghost may now fit where it did not.
+    show_cursor(world)
+}
+
+// Returns the text shown while the mouse is over a building or its label.
+fn building_tooltip(building: &Building) -> String {
+    format!(
+        "{}\nBuffer: {}/{}",
+        String::from(&building.state),
+        building.buffer.count(),
+        building.buffer.capacity()
+    )
+}
+
+// Returns the text shown while the mouse is over a belt.
+fn belt_tooltip(stats: &Stats) -> String {
+    format!(
+        "Belt\n{:.0} items/min\nBlocked: {:.0}%\nStarved: {:.0}%",
+        stats.items_per_minute(),
+        stats.blocked_percent(),
+        stats.starved_percent()
+    )
+}
+
+// Shows the current state of the buildings and belts in their labels and tooltips. Sprites whose
+// text has not changed are left alone so they are not drawn again.
+fn update_labels(world: &mut World<Hex, HexLayout>) {
+    GAME_STATE.with(|game_state| {
+        let game_state = game_state.borrow();
+
+        for building in game_state.buildings.values() {
+            let tooltip = building_tooltip(building);
+
+            if let Some(label) = world
+                .viewport
+                .layer_mut("labels")
+                .and_then(|layer| layer.sprite_mut(&building.coord))
+            {
+                if label.tooltip() != Some(tooltip.as_str()) {
+                    let state = String::from(&building.state);
+                    label.set_shape(&Shape::text(&state, "sans-serif", LABEL_FONT_SIZE));
+                    label.set_tooltip(Some(&tooltip));
+                }
+            }
+            if let Some(sprite) = world
+                .viewport
+                .layer_mut("buildings")
+                .and_then(|layer| layer.sprite_mut(&building.coord))
+            {
+                if sprite.tooltip() != Some(tooltip.as_str()) {
+                    sprite.set_tooltip(Some(&tooltip));
+                }
+            }
+        }
+
+        for belt in game_state.belts.values() {
+            let tooltip = belt_tooltip(belt.items.stats());
+
+            for coord in &belt.path {
+                if let Some(sprite) = world
+                    .viewport
+                    .layer_mut("belts")
+                    .and_then(|layer| layer.sprite_mut(coord))
+                {
+                    if sprite.tooltip() != Some(tooltip.as_str()) {
+                        sprite.set_tooltip(Some(&tooltip));
+                    }
+                }
+            }
+        }
+    });
+}
+
+// Returns a sprite showing a cell of a placed belt.
+fn belt_sprite(position: &Point) -> Sprite {
+    let mut texture = Texture::named("belt");
+    texture.border = Some(TextureBorder::new(1, "black"));
+
+    Sprite::new(&rng::uid().to_string(), &Shape::Cell, position, &texture)
+}
+
+// Shows the cells of a belt placed along `path`.
+fn add_belt_sprites<C>(world: &mut World<Hex, HexLayout>, path: &[C])
+where
+    C: Cell,
+{
+    for cell in path {
+        let position = world.layout.pixel_from_coord(&cell.coord());
+        if let Some(layer) = world.viewport.layer_mut("belts") {
+            layer.add_sprite(cell.coord(), belt_sprite(&position));
+        }
+    }
+}
+
 fn show_belt_preview<C>(path: &[C])
 where
     C: Cell,
@@ -390,23 +1062,37 @@ where
         w.borrow_mut().clear_layer("belt_preview");
     });
 
-    let belt_shape = Shape::Cell;
+    let belt = Belt::from_path(path);
 
-    for c in path {
+    for (i, c) in path.iter().enumerate() {
         // This works because it is able to infer the type of Cell from the function calls.
         let cell = Cell::new(c.coord().x as f32, c.coord().y as f32, c.coord().z as f32);
 
         let position = WORLD.with(|w| -> Point { w.borrow().layout.cell_to_pixel(&cell) });
 
-        let texture = {
-            let mut tex = Texture::new();
-            let tex_border = TextureBorder::new(1, "black");
-            tex.border = Some(tex_border);
-            tex.background_color = Some("gray".to_owned());
-            tex
-        };
+        // Join the middle of the edges shared with the previous and next cells through the
+        // center of this one, so the cells of the belt form one line.
+        let mut points = Vec::new();
+        if i > 0 {
+            points.push(belt_edge_center(&cell, &path[i - 1].coord()));
+        }
+        points.push(Point::origin());
+        if i + 1 < path.len() {
+            points.push(belt_edge_center(&cell, &path[i + 1].coord()));
+        }
+
+        let mut stroke = Stroke::new(BELT_PREVIEW_WIDTH, "gray");
+        // Underground entries and exits are dashed and darker to show where the belt goes beneath.
+        if belt.is_underground(&cell.coord()) {
+            stroke.color = "dimgray".to_owned();
+            stroke.dash = vec![BELT_PREVIEW_WIDTH, BELT_PREVIEW_WIDTH];
+        }
+        // Point the end of the belt in the direction it moves.
+        stroke.end_arrow = i + 1 == path.len();
+
+        let belt_shape = Shape::Path { points, stroke };
         let sprite_id = rng::uid().to_string();
-        let belt_preview = Sprite::new(&sprite_id, &belt_shape, &position, &texture);
+        let belt_preview = Sprite::new(&sprite_id, &belt_shape, &position, &Texture::new());
 
         WORLD.with(|w| {
             w.borrow_mut()
@@ -421,3 +1107,35 @@ where
         w.borrow_mut().render_layer("belt_preview");
     });
 }
+
+// Returns where a belt through `cell` leaves it towards the cell at `other`, about the center of
+// `cell`. This is the middle of their shared edge, or half way to `other` when the belt goes
+// underground between them.
+fn belt_edge_center(cell: &Hex, other: &CellCoord) -> Point {
+    WORLD.with(|w| -> Point {
+        let layout = &w.borrow().layout;
+
+        for direction in cell.directions() {
+            if cell.neighbor(*direction).coord() == *other {
+                return layout.shared_edge_center(cell, *direction);
+            }
+        }
+
+        let other_center = layout.pixel_from_coord(other);
+        &(&other_center - &layout.cell_to_pixel(cell)) / 2.0
+    })
+}
+
+// Slowly grows and shrinks a building.
+fn working_animation() -> Animation {
+    let mut animation = Animation::new(1200.0);
+    animation.easing = Easing::EaseInOut;
+    animation.repeat = true;
+    animation.scale = vec![
+        Keyframe::new(0.0, 1.0),
+        Keyframe::new(0.5, 1.15),
+        Keyframe::new(1.0, 1.0),
+    ];
+
+    animation
+}
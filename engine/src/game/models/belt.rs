@@ -1,12 +1,58 @@
+use std::cmp;
 use std::collections::VecDeque;
+use std::convert::{Infallible, TryFrom};
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::stats::Stats;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Error {
     Empty,
     Full,
+    OutOfRange,
+    /// Saved chunks that do not add up to the capacity and item count of the belt
+    Corrupt,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Empty => write!(f, "belt is empty"),
+            Error::Full => write!(f, "belt is full"),
+            Error::OutOfRange => write!(f, "position is out of range"),
+            Error::Corrupt => write!(f, "belt chunks do not match its capacity and item count"),
+        }
+    }
+}
+
+/// Number of sub-slot units that make up one slot on a belt
+pub const SUBSLOTS: usize = 8;
+
+/// Speed tier of a belt
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Speed {
+    Slow,
+    Normal,
+    Fast,
+    Express,
 }
 
-#[derive(Debug)]
+impl Speed {
+    /// Returns the distance, in sub-slots, items move in one tick
+    pub fn subslots_per_tick(self) -> usize {
+        match self {
+            Speed::Slow => 1,
+            Speed::Normal => 2,
+            Speed::Fast => 4,
+            Speed::Express => 8,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 struct Chunk<T> {
     item: Option<T>,
     repeat: usize,
@@ -43,7 +89,8 @@ impl<T> Chunk<T> {
         self.item.is_some()
     }
 }
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(try_from = "SavedBelt<T>")]
 pub struct Belt<T> {
     /// Chunks on the belt
     chunks: VecDeque<Chunk<T>>,
@@ -53,6 +100,60 @@ pub struct Belt<T> {
 
     /// Number of items in the belt currently
     item_count: usize,
+
+    /// Speed tier of the belt
+    speed: Speed,
+
+    /// Sub-slot progress of the moving items towards their next slot
+    offset: usize,
+
+    /// Throughput and occupancy statistics of the belt
+    #[serde(skip)]
+    stats: Stats,
+}
+
+/// A belt as read from a save, before it is checked
+#[derive(Deserialize)]
+struct SavedBelt<T> {
+    chunks: VecDeque<Chunk<T>>,
+    capacity: usize,
+    item_count: usize,
+    speed: Speed,
+    offset: usize,
+}
+
+impl<T> TryFrom<SavedBelt<T>> for Belt<T> {
+    type Error = Error;
+
+    /// Rejects saves whose chunks would break the belt once it moves
+    fn try_from(saved: SavedBelt<T>) -> Result<Self, Self::Error> {
+        let slots: usize = saved.chunks.iter().map(|chunk| chunk.repeat).sum();
+        let items: usize = saved
+            .chunks
+            .iter()
+            .filter(|chunk| chunk.is_item())
+            .map(|chunk| chunk.repeat)
+            .sum();
+        let empty_chunk = saved.capacity > 0 && saved.chunks.iter().any(|chunk| chunk.repeat == 0);
+
+        if saved.chunks.is_empty()
+            || slots != saved.capacity
+            || items != saved.item_count
+            || empty_chunk
+            || saved.offset >= SUBSLOTS
+        {
+            return Err(Error::Corrupt);
+        }
+
+        Ok(Belt {
+            chunks: saved.chunks,
+            capacity: saved.capacity,
+            item_count: saved.item_count,
+            speed: saved.speed,
+            offset: saved.offset,
+            stats: Stats::default(),
+        })
+    }
 }
 
 impl<T> Belt<T>
@@ -61,10 +162,18 @@ where
 {
     /// Creates a belt with the specified capacity
     pub fn new(capacity: usize) -> Belt<T> {
+        Belt::with_speed(capacity, Speed::Normal)
+    }
+
+    /// Creates a belt with the specified capacity and speed tier
+    pub fn with_speed(capacity: usize, speed: Speed) -> Belt<T> {
         let mut belt = Belt {
             chunks: VecDeque::new(),
             capacity,
             item_count: 0,
+            speed,
+            offset: 0,
+            stats: Stats::default(),
         };
 
         belt.init();
@@ -75,6 +184,7 @@ where
     fn init(&mut self) {
         self.chunks.push_back(Chunk::spaces(self.capacity));
         self.item_count = 0;
+        self.offset = 0;
     }
 
     /// Returns the number of items this belt can hold
@@ -109,6 +219,146 @@ where
         unreachable!("should always have a non-zero length chunk on belt");
     }
 
+    /// Returns the speed tier of the belt
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    /// Changes the speed tier of the belt
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+    }
+
+    /// Returns the sub-slot progress of the moving items towards their next
+    /// slot
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns an iterator over the items on the belt and their positions,
+    /// starting from the front of the belt
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        let mut end = self.capacity;
+
+        self.chunks.iter().flat_map(move |chunk| {
+            let start = end - chunk.repeat;
+            let top = end;
+            end = start;
+
+            chunk
+                .item
+                .iter()
+                .flat_map(move |item| (start..top).rev().map(move |pos| (pos, item)))
+        })
+    }
+
+    /// Returns the item at `position` on the belt
+    pub fn item_at(&self, position: usize) -> Option<&T> {
+        let (index, _) = self.locate(position)?;
+
+        self.chunks[index].item.as_ref()
+    }
+
+    /// Puts an item at `position` on the belt
+    pub fn insert_at(&mut self, position: usize, item: T) -> Result<(), Error> {
+        let (index, offset) = self.locate(position).ok_or(Error::OutOfRange)?;
+
+        if self.chunks[index].is_item() {
+            return Err(Error::Full);
+        }
+
+        let index = self.isolate(index, offset);
+        self.chunks[index].item = Some(item);
+        self.compact();
+
+        self.item_count += 1;
+        self.stats.record_entered();
+
+        Ok(())
+    }
+
+    /// Takes the item at `position` off the belt
+    pub fn remove_at(&mut self, position: usize) -> Result<T, Error> {
+        let (index, offset) = self.locate(position).ok_or(Error::OutOfRange)?;
+
+        if self.chunks[index].is_space() {
+            return Err(Error::Empty);
+        }
+
+        let index = self.isolate(index, offset);
+        let item = self.chunks[index].item.take().unwrap();
+        self.compact();
+
+        self.item_count -= 1;
+        self.stats.record_exited();
+
+        Ok(item)
+    }
+
+    /// Returns the index of the chunk covering `position` and how far into
+    /// that chunk, counting from its front, the position lies
+    fn locate(&self, position: usize) -> Option<(usize, usize)> {
+        if position >= self.capacity {
+            return None;
+        }
+
+        let mut end = self.capacity;
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let start = end - chunk.repeat;
+
+            if position >= start && position < end {
+                return Some((index, end - 1 - position));
+            }
+
+            end = start;
+        }
+
+        unreachable!("chunks should cover the full capacity of the belt");
+    }
+
+    /// Splits the chunk at `index` so the slot `offset` into it becomes a
+    /// chunk of its own. Returns the index of the new single slot chunk.
+    fn isolate(&mut self, index: usize, offset: usize) -> usize {
+        let chunk = &mut self.chunks[index];
+        let after = chunk.repeat - offset - 1;
+        let item = chunk.item.clone();
+
+        chunk.repeat = offset;
+
+        self.chunks.insert(
+            index + 1,
+            Chunk {
+                item: item.clone(),
+                repeat: 1,
+            },
+        );
+        self.chunks.insert(
+            index + 2,
+            Chunk {
+                item,
+                repeat: after,
+            },
+        );
+
+        index + 1
+    }
+
+    /// Returns the throughput and occupancy statistics of the belt
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Returns the rate items exit the belt, in items per minute
+    pub fn items_per_minute(&self) -> f32 {
+        self.stats.items_per_minute()
+    }
+
+    /// Returns the average percentage of the belt occupied by items
+    pub fn saturation(&self) -> f32 {
+        self.stats.saturation(self.capacity)
+    }
+
     /// Indicates if `take()` can be successfully called
     pub fn can_take(&self) -> bool {
         self.peek_front().is_some()
@@ -163,6 +413,7 @@ where
         }
 
         self.item_count -= 1;
+        self.stats.record_exited();
 
         Ok(item)
     }
@@ -192,40 +443,220 @@ where
         }
 
         self.item_count += 1;
+        self.stats.record_entered();
 
         Ok(())
     }
 
     fn push_back_space(&mut self) {
+        self.push_back_spaces(1);
+    }
+
+    fn push_back_spaces(&mut self, count: usize) {
         let back = self.chunks.back_mut().unwrap();
 
         if back.is_space() {
-            back.repeat += 1;
+            back.repeat += count;
         } else {
-            self.chunks.push_back(Chunk::space());
+            self.chunks.push_back(Chunk::spaces(count));
         }
     }
 
+    /// Removes zero-length chunks and merges neighbouring chunks that hold
+    /// the same item
+    fn compact(&mut self) {
+        let mut compacted: VecDeque<Chunk<T>> = VecDeque::with_capacity(self.chunks.len());
+
+        for chunk in self.chunks.drain(..) {
+            if chunk.repeat == 0 {
+                continue;
+            }
+
+            match compacted.back_mut() {
+                Some(back) if back.item == chunk.item => back.repeat += chunk.repeat,
+                _ => compacted.push_back(chunk),
+            }
+        }
+
+        if compacted.is_empty() {
+            compacted.push_back(Chunk::spaces(0));
+        }
+
+        self.chunks = compacted;
+    }
+
+    /// Returns `Some(position)` where all items before `position` are free to
+    /// move forward. Returns `None` if the belt has no space.
+    pub fn moving_before(&self) -> Option<usize> {
+        let mut pos = self.capacity;
+
+        for chunk in self.chunks.iter() {
+            if chunk.is_space() && chunk.repeat > 0 {
+                return Some(pos);
+            }
+
+            pos -= chunk.repeat;
+        }
+
+        None
+    }
+
     /// Advances the belt one space forward. Returns `Some(position)` to
     /// indicate that all items before `position` moved forward one position.
     /// Returns `None` if the belt has no space.
     pub fn advance(&mut self) -> Option<usize> {
-        let mut pos = self.capacity;
+        let pos = self.moving_before()?;
+
+        let chunk = self
+            .chunks
+            .iter_mut()
+            .find(|chunk| chunk.is_space() && chunk.repeat > 0)
+            .unwrap();
+        chunk.repeat -= 1;
+
+        self.push_back_space();
+        Some(pos)
+    }
 
+    /// Advances the belt `steps` spaces forward in a single pass, as if
+    /// `advance()` had been called `steps` times. Returns `Some(position)` to
+    /// indicate the furthest position before which items moved. Returns
+    /// `None` if the belt has no space or `steps` is zero.
+    pub fn advance_by(&mut self, steps: usize) -> Option<usize> {
+        if steps == 0 {
+            return None;
+        }
+
+        let moved = self.moving_before()?;
+
+        let mut remaining = steps;
         for chunk in self.chunks.iter_mut() {
-            if chunk.is_space() && chunk.repeat > 0 {
-                chunk.repeat -= 1;
+            if remaining == 0 {
+                break;
+            }
 
-                self.push_back_space();
-                return Some(pos);
+            if chunk.is_space() {
+                let consumed = cmp::min(remaining, chunk.repeat);
+                chunk.repeat -= consumed;
+                remaining -= consumed;
             }
+        }
 
-            pos -= chunk.repeat;
+        // Any steps left over only cycle spaces through the back of the belt.
+        self.push_back_spaces(steps - remaining);
+        self.compact();
+
+        Some(moved)
+    }
+
+    /// Moves the belt forward by its speed for one tick. Returns the
+    /// furthest `position` passed to `advance()` during the tick, or `None` if
+    /// no item crossed into a new slot.
+    pub fn tick(&mut self) -> Option<usize> {
+        self.stats
+            .end_tick(self.can_take(), self.can_put(), self.item_count);
+
+        if self.moving_before().is_none() {
+            self.offset = 0;
+            return None;
+        }
+
+        self.offset += self.speed.subslots_per_tick();
+
+        let moved = self.advance_by(self.offset / SUBSLOTS);
+        self.offset %= SUBSLOTS;
+
+        if self.moving_before().is_none() {
+            self.offset = 0;
+        }
+
+        moved
+    }
+
+    /// Indicates that no item on the belt can move, so ticking the belt
+    /// changes nothing until an item is put or taken
+    pub fn is_idle(&self) -> bool {
+        let mut seen_space = false;
+
+        for chunk in self.chunks.iter() {
+            if chunk.repeat == 0 {
+                continue;
+            }
+
+            if chunk.is_space() {
+                seen_space = true;
+            } else if seen_space {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Returns the number of `advance()` calls before an item reaches the
+    /// front of the belt, or `None` if the belt is empty
+    pub fn steps_until_front(&self) -> Option<usize> {
+        let mut steps = 0;
+
+        for chunk in self.chunks.iter() {
+            if chunk.is_item() && chunk.repeat > 0 {
+                return Some(steps);
+            }
+
+            steps += chunk.repeat;
         }
 
         None
     }
 
+    /// Returns the number of `advance()` calls before the back of the belt
+    /// has space, or `None` if the belt is full
+    pub fn steps_until_space(&self) -> Option<usize> {
+        if self.can_put() {
+            Some(0)
+        } else if self.item_count < self.capacity {
+            Some(1)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of `tick()` calls before an item reaches the front
+    /// of the belt, or `None` if the belt is empty
+    pub fn ticks_until_front(&self) -> Option<usize> {
+        self.steps_until_front()
+            .map(|steps| self.ticks_for_steps(steps))
+    }
+
+    /// Returns the number of `tick()` calls before the back of the belt has
+    /// space, or `None` if the belt is full
+    pub fn ticks_until_space(&self) -> Option<usize> {
+        self.steps_until_space()
+            .map(|steps| self.ticks_for_steps(steps))
+    }
+
+    fn ticks_for_steps(&self, steps: usize) -> usize {
+        if steps == 0 {
+            return 0;
+        }
+
+        let distance = steps * SUBSLOTS - self.offset;
+        let speed = self.speed.subslots_per_tick();
+
+        distance.div_ceil(speed)
+    }
+
+    /// Returns the location, in sub-slots, of the item at slot `position`
+    /// including the progress it has made towards the next slot
+    pub fn subslot_position(&self, position: usize) -> usize {
+        let base = position * SUBSLOTS;
+
+        match self.moving_before() {
+            Some(before) if position < before => base + self.offset,
+            _ => base,
+        }
+    }
+
     /// Clears the belt
     pub fn clear(&mut self) {
         self.chunks.clear();
@@ -233,8 +664,66 @@ where
     }
 }
 
+/// Writes the belt from back to front, one character per slot, with `.`
+/// marking an empty slot. Items are expected to display as one character.
+impl<T> fmt::Display for Belt<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for chunk in self.chunks.iter().rev() {
+            for _ in 0..chunk.repeat {
+                match &chunk.item {
+                    Some(item) => write!(f, "{}", item)?,
+                    None => write!(f, ".")?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a belt written by `Display`, where every character is a slot and `.`
+/// marks an empty slot.
+impl<T> FromStr for Belt<T>
+where
+    T: Clone + Eq + From<char>,
+{
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut belt = Belt {
+            chunks: VecDeque::new(),
+            capacity: 0,
+            item_count: 0,
+            speed: Speed::Normal,
+            offset: 0,
+            stats: Stats::default(),
+        };
+
+        for c in s.chars().rev() {
+            let chunk = match c {
+                '.' => Chunk::space(),
+                _ => {
+                    belt.item_count += 1;
+                    Chunk::item(T::from(c))
+                }
+            };
+
+            belt.capacity += 1;
+            belt.chunks.push_back(chunk);
+        }
+
+        belt.compact();
+
+        Ok(belt)
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use super::super::stats::TICKS_PER_MINUTE;
     use super::*;
 
     #[test]
@@ -288,6 +777,7 @@ mod test {
         assert!(!belt.can_put());
         assert_eq!(belt.capacity(), CAPACITY);
         assert_eq!(belt.count(), 5);
+        assert_eq!(belt.to_string(), "54321");
     }
 
     #[test]
@@ -304,6 +794,7 @@ mod test {
         assert!(!belt.can_put());
         assert_eq!(belt.capacity(), CAPACITY);
         assert_eq!(belt.count(), 5);
+        assert_eq!(belt.to_string(), "54321");
 
         for i in 1..=CAPACITY {
             assert_eq!(belt.take(), Ok(i));
@@ -325,7 +816,7 @@ mod test {
         belt.advance();
         belt.advance();
 
-        dbg!(&belt);
+        assert_eq!(belt.to_string(), "..a..");
         assert!(!belt.can_take());
         assert!(belt.can_put());
         assert_eq!(belt.capacity(), CAPACITY);
@@ -343,7 +834,7 @@ mod test {
         belt.advance();
         belt.advance();
 
-        dbg!(&belt);
+        assert_eq!(belt.to_string(), "....a");
         assert!(belt.can_take());
         assert!(belt.can_put());
         assert_eq!(belt.capacity(), CAPACITY);
@@ -366,7 +857,242 @@ mod test {
 
         belt.put('d');
 
-        dbg!(&belt);
+        assert_eq!(belt.to_string(), "d.cba");
         assert_eq!(belt.advance(), Some(2));
+        assert_eq!(belt.to_string(), ".dcba");
+    }
+
+    #[test]
+    fn tick_speed_01() {
+        const CAPACITY: usize = 5;
+        let mut belt = Belt::with_speed(CAPACITY, Speed::Express);
+
+        assert_eq!(belt.put('a'), Ok(()));
+
+        for _ in 0..CAPACITY - 1 {
+            assert_eq!(belt.tick(), Some(CAPACITY));
+            assert_eq!(belt.offset(), 0);
+        }
+
+        assert!(belt.can_take());
+    }
+
+    #[test]
+    fn tick_speed_02() {
+        const CAPACITY: usize = 5;
+        let mut belt = Belt::with_speed(CAPACITY, Speed::Normal);
+
+        assert_eq!(belt.put('a'), Ok(()));
+
+        assert_eq!(belt.tick(), None);
+        assert_eq!(belt.offset(), 2);
+        assert_eq!(belt.subslot_position(0), 2);
+        assert_eq!(belt.tick(), None);
+        assert_eq!(belt.tick(), None);
+        assert_eq!(belt.subslot_position(0), 6);
+        assert_eq!(belt.tick(), Some(CAPACITY));
+        assert_eq!(belt.offset(), 0);
+        assert_eq!(belt.subslot_position(1), SUBSLOTS);
+    }
+
+    #[test]
+    fn tick_blocked_01() {
+        const CAPACITY: usize = 2;
+        let mut belt = Belt::with_speed(CAPACITY, Speed::Fast);
+
+        assert_eq!(belt.put('a'), Ok(()));
+        assert_eq!(belt.advance(), Some(CAPACITY));
+        assert_eq!(belt.put('b'), Ok(()));
+
+        assert_eq!(belt.tick(), None);
+        assert_eq!(belt.offset(), 0);
+        assert_eq!(belt.subslot_position(0), 0);
+        assert_eq!(belt.subslot_position(1), SUBSLOTS);
+    }
+
+    fn gapped_belt() -> Belt<char> {
+        ".c..b.aa".parse().unwrap()
+    }
+
+    #[test]
+    fn advance_by_01() {
+        for steps in 0..12 {
+            let mut stepped = gapped_belt();
+            let mut skipped = gapped_belt();
+
+            for _ in 0..steps {
+                stepped.advance();
+            }
+            skipped.advance_by(steps);
+
+            assert_eq!(stepped.to_string(), skipped.to_string());
+            assert_eq!(stepped.count(), skipped.count());
+        }
+    }
+
+    #[test]
+    fn advance_by_02() {
+        let mut belt: Belt<u32> = Belt::new(5);
+
+        assert_eq!(belt.advance_by(0), None);
+        assert_eq!(belt.advance_by(3), Some(5));
+
+        for i in 1..=5 {
+            assert_eq!(belt.put(i), Ok(()));
+            belt.advance();
+        }
+
+        assert_eq!(belt.advance_by(3), None);
+        assert!(belt.is_idle());
+    }
+
+    #[test]
+    fn steps_until_01() {
+        let mut belt = gapped_belt();
+
+        assert!(!belt.is_idle());
+        assert_eq!(belt.steps_until_front(), Some(0));
+        assert_eq!(belt.take(), Ok('a'));
+        assert_eq!(belt.steps_until_front(), Some(1));
+
+        belt.advance_by(4);
+
+        assert!(belt.is_idle());
+        assert_eq!(belt.steps_until_front(), Some(0));
+        assert_eq!(belt.steps_until_space(), Some(0));
+    }
+
+    #[test]
+    fn ticks_until_01() {
+        let mut belt: Belt<char> = Belt::with_speed(5, Speed::Normal);
+
+        assert_eq!(belt.ticks_until_front(), None);
+
+        assert_eq!(belt.put('a'), Ok(()));
+
+        assert_eq!(belt.steps_until_space(), Some(1));
+        assert_eq!(belt.ticks_until_space(), Some(SUBSLOTS / 2));
+        assert_eq!(belt.ticks_until_front(), Some(4 * SUBSLOTS / 2));
+
+        belt.tick();
+
+        assert_eq!(belt.ticks_until_front(), Some(4 * SUBSLOTS / 2 - 1));
+    }
+
+    #[test]
+    fn iter_01() {
+        let belt = gapped_belt();
+
+        let items: Vec<(usize, char)> = belt.iter().map(|(pos, item)| (pos, *item)).collect();
+
+        assert_eq!(items, vec![(7, 'a'), (6, 'a'), (4, 'b'), (1, 'c')]);
+        assert_eq!(belt.item_at(6), Some(&'a'));
+        assert_eq!(belt.item_at(5), None);
+        assert_eq!(belt.item_at(8), None);
+    }
+
+    #[test]
+    fn insert_at_01() {
+        let mut belt = gapped_belt();
+
+        assert_eq!(belt.insert_at(6, 'x'), Err(Error::Full));
+        assert_eq!(belt.insert_at(8, 'x'), Err(Error::OutOfRange));
+        assert_eq!(belt.insert_at(3, 'x'), Ok(()));
+        assert_eq!(belt.insert_at(5, 'a'), Ok(()));
+
+        assert_eq!(belt.to_string(), ".c.xbaaa");
+        assert_eq!(belt.count(), 6);
+        assert_eq!(belt.advance(), Some(3));
+    }
+
+    #[test]
+    fn remove_at_01() {
+        let mut belt = gapped_belt();
+
+        assert_eq!(belt.remove_at(5), Err(Error::Empty));
+        assert_eq!(belt.remove_at(8), Err(Error::OutOfRange));
+        assert_eq!(belt.remove_at(7), Ok('a'));
+        assert_eq!(belt.remove_at(4), Ok('b'));
+
+        assert_eq!(belt.to_string(), ".c....a.");
+        assert_eq!(belt.count(), 2);
+        assert_eq!(belt.steps_until_front(), Some(1));
+    }
+
+    #[test]
+    fn parse_01() {
+        let belt: Belt<char> = "..aa.b".parse().unwrap();
+
+        assert_eq!(belt.capacity(), 6);
+        assert_eq!(belt.count(), 3);
+        assert_eq!(belt.peek_front(), Some(&'b'));
+        assert_eq!(belt.peek_back(), None);
+        assert_eq!(belt.chunks.len(), 4);
+        assert_eq!(belt.to_string(), "..aa.b");
+    }
+
+    #[test]
+    fn serde_01() {
+        let mut belt: Belt<char> = ".c..b.aa".parse().unwrap();
+        belt.set_speed(Speed::Fast);
+        belt.tick();
+
+        let saved = ron::ser::to_string(&belt).unwrap();
+        let loaded: Belt<char> = ron::de::from_str(&saved).unwrap();
+
+        assert_eq!(loaded.to_string(), belt.to_string());
+        assert_eq!(loaded.chunks.len(), belt.chunks.len());
+        assert_eq!(loaded.count(), belt.count());
+        assert_eq!(loaded.speed(), Speed::Fast);
+        assert_eq!(loaded.offset(), belt.offset());
+    }
+
+    #[test]
+    fn serde_02() {
+        // Chunks covering 3 slots of a belt of 4.
+        let corrupt = "(chunks: [(item: None, repeat: 2), (item: Some('a'), repeat: 1)], \
+                       capacity: 4, item_count: 1, speed: Normal, offset: 0)";
+        assert!(ron::de::from_str::<Belt<char>>(corrupt).is_err());
+
+        // Items that do not match the item count.
+        let corrupt = "(chunks: [(item: None, repeat: 3), (item: Some('a'), repeat: 1)], \
+                       capacity: 4, item_count: 2, speed: Normal, offset: 0)";
+        assert!(ron::de::from_str::<Belt<char>>(corrupt).is_err());
+
+        let valid = "(chunks: [(item: None, repeat: 3), (item: Some('a'), repeat: 1)], \
+                     capacity: 4, item_count: 1, speed: Normal, offset: 0)";
+        let belt = ron::de::from_str::<Belt<char>>(valid).unwrap();
+        assert_eq!(belt.to_string(), "a...");
+    }
+
+    #[test]
+    fn stats_01() {
+        const CAPACITY: usize = 4;
+        let mut belt = Belt::with_speed(CAPACITY, Speed::Express);
+        let mut entered = 0;
+        let mut exited = 0;
+
+        for i in 0..8 {
+            if belt.put(i).is_ok() {
+                entered += 1;
+            }
+            if i % 4 == 3 && belt.take().is_ok() {
+                exited += 1;
+            }
+            belt.tick();
+        }
+
+        let totals = belt.stats().totals();
+
+        assert_eq!(belt.stats().ticks(), 8);
+        assert_eq!(totals.entered, entered);
+        assert_eq!(totals.exited, exited);
+        assert_eq!(totals.entered - totals.exited, belt.count());
+        assert!(totals.blocked > 0);
+        assert!(belt.saturation() > 0.0 && belt.saturation() <= 100.0);
+        assert_eq!(
+            belt.items_per_minute(),
+            exited as f32 * TICKS_PER_MINUTE as f32 / 8.0
+        );
     }
 }
@@ -0,0 +1,75 @@
+use std::collections::VecDeque;
+
+use super::inserter::{Sink, Source};
+
+/// Internal storage of a building
+#[derive(Debug, Clone)]
+pub struct Buffer<T> {
+    /// Items held in the buffer, oldest first
+    items: VecDeque<T>,
+
+    /// Number of items the buffer can hold
+    capacity: usize,
+}
+
+impl<T> Buffer<T>
+where
+    T: Clone + Eq,
+{
+    /// Creates a buffer with the specified capacity
+    pub fn new(capacity: usize) -> Buffer<T> {
+        Buffer {
+            items: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Returns the number of items this buffer can hold
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of items the buffer holds currently
+    pub fn count(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns the number of `item` the buffer holds currently
+    pub fn count_of(&self, item: &T) -> usize {
+        self.items.iter().filter(|it| *it == item).count()
+    }
+
+    /// Indicates if the buffer cannot hold any more items
+    pub fn is_full(&self) -> bool {
+        self.items.len() >= self.capacity
+    }
+}
+
+impl<T> Source<T> for Buffer<T>
+where
+    T: Clone + Eq,
+{
+    fn take_matching(&mut self, filter: Option<&T>) -> Option<T> {
+        let index = match filter {
+            Some(wanted) => self.items.iter().position(|it| it == wanted)?,
+            None => 0,
+        };
+
+        self.items.remove(index)
+    }
+}
+
+impl<T> Sink<T> for Buffer<T>
+where
+    T: Clone + Eq,
+{
+    fn put_item(&mut self, item: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(item);
+        }
+
+        self.items.push_back(item);
+
+        Ok(())
+    }
+}
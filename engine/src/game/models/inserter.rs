@@ -0,0 +1,261 @@
+use super::belt::Belt;
+
+/// Something an inserter can pick items from
+pub trait Source<T> {
+    /// Takes an item equal to `filter`, or any item if there is no filter
+    fn take_matching(&mut self, filter: Option<&T>) -> Option<T>;
+}
+
+/// Something an inserter can drop items into
+pub trait Sink<T> {
+    /// Puts an item into the sink, handing it back if there is no room
+    fn put_item(&mut self, item: T) -> Result<(), T>;
+}
+
+/// A single slot of a belt that an inserter reaches into
+pub struct BeltSlot<'a, T> {
+    pub belt: &'a mut Belt<T>,
+    pub position: usize,
+}
+
+impl<'a, T> BeltSlot<'a, T> {
+    pub fn new(belt: &'a mut Belt<T>, position: usize) -> BeltSlot<'a, T> {
+        BeltSlot { belt, position }
+    }
+}
+
+impl<'a, T> Source<T> for BeltSlot<'a, T>
+where
+    T: Clone + Eq,
+{
+    fn take_matching(&mut self, filter: Option<&T>) -> Option<T> {
+        let item = self.belt.item_at(self.position)?;
+
+        if filter.is_some_and(|wanted| wanted != item) {
+            return None;
+        }
+
+        self.belt.remove_at(self.position).ok()
+    }
+}
+
+impl<'a, T> Sink<T> for BeltSlot<'a, T>
+where
+    T: Clone + Eq,
+{
+    fn put_item(&mut self, item: T) -> Result<(), T> {
+        if self.belt.item_at(self.position).is_some() || self.position >= self.belt.capacity() {
+            return Err(item);
+        }
+
+        self.belt
+            .insert_at(self.position, item)
+            .expect("slot should be empty");
+
+        Ok(())
+    }
+}
+
+/// What an inserter is doing during a tick
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum State {
+    /// Waiting at the pick side for an item
+    Waiting,
+    /// Carrying an item to the drop side with the ticks left in the swing
+    Delivering(usize),
+    /// Waiting at the drop side for room to drop the item
+    Blocked,
+    /// Returning to the pick side with the ticks left in the swing
+    Returning(usize),
+}
+
+/// Moves items from the hex in `direction` to the hex opposite it
+#[derive(Debug)]
+pub struct Inserter<T> {
+    /// Direction of the hex items are picked from
+    direction: i32,
+
+    /// Number of ticks to swing from one side to the other
+    swing_ticks: usize,
+
+    /// Only items equal to the filter are picked, when set
+    filter: Option<T>,
+
+    /// Item being carried
+    hand: Option<T>,
+
+    /// Current state of the inserter
+    state: State,
+}
+
+impl<T> Inserter<T>
+where
+    T: Clone + Eq,
+{
+    /// Creates an inserter picking from `direction` that takes `swing_ticks`
+    /// to swing between sides
+    pub fn new(direction: i32, swing_ticks: usize) -> Inserter<T> {
+        Inserter {
+            direction,
+            swing_ticks,
+            filter: None,
+            hand: None,
+            state: State::Waiting,
+        }
+    }
+
+    /// Returns the direction of the hex items are picked from
+    pub fn direction(&self) -> i32 {
+        self.direction
+    }
+
+    /// Returns the number of ticks to swing from one side to the other
+    pub fn swing_ticks(&self) -> usize {
+        self.swing_ticks
+    }
+
+    /// Returns the item filter
+    pub fn filter(&self) -> Option<&T> {
+        self.filter.as_ref()
+    }
+
+    /// Changes the item filter
+    pub fn set_filter(&mut self, filter: Option<T>) {
+        self.filter = filter;
+    }
+
+    /// Returns the item being carried
+    pub fn hand(&self) -> Option<&T> {
+        self.hand.as_ref()
+    }
+
+    /// Returns the current state of the inserter
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Moves the inserter forward one tick, picking from `source` and
+    /// dropping into `sink`
+    pub fn tick<S, D>(&mut self, source: &mut S, sink: &mut D) -> State
+    where
+        S: Source<T>,
+        D: Sink<T>,
+    {
+        self.state = match self.state {
+            State::Waiting => match source.take_matching(self.filter.as_ref()) {
+                Some(item) => {
+                    self.hand = Some(item);
+                    State::Delivering(self.swing_ticks)
+                }
+                None => State::Waiting,
+            },
+            State::Delivering(ticks) if ticks > 1 => State::Delivering(ticks - 1),
+            State::Delivering(_) | State::Blocked => self.drop_into(sink),
+            State::Returning(ticks) if ticks > 1 => State::Returning(ticks - 1),
+            State::Returning(_) => State::Waiting,
+        };
+
+        self.state
+    }
+
+    fn drop_into<D>(&mut self, sink: &mut D) -> State
+    where
+        D: Sink<T>,
+    {
+        let item = self
+            .hand
+            .take()
+            .expect("inserter should hold an item when dropping");
+
+        match sink.put_item(item) {
+            Ok(()) => State::Returning(self.swing_ticks),
+            Err(item) => {
+                self.hand = Some(item);
+                State::Blocked
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::buffer::Buffer;
+    use super::*;
+
+    #[test]
+    fn belt_to_buffer_01() {
+        let mut belt: Belt<char> = "...ab".parse().unwrap();
+        let mut buffer: Buffer<char> = Buffer::new(1);
+        let mut inserter = Inserter::new(0, 2);
+
+        let mut states = Vec::new();
+        for _ in 0..6 {
+            states.push(inserter.tick(&mut BeltSlot::new(&mut belt, 4), &mut buffer));
+        }
+
+        assert_eq!(
+            states,
+            vec![
+                State::Delivering(2),
+                State::Delivering(1),
+                State::Returning(2),
+                State::Returning(1),
+                State::Waiting,
+                State::Waiting,
+            ]
+        );
+        assert_eq!(belt.to_string(), "...a.");
+        assert_eq!(buffer.count_of(&'b'), 1);
+        assert_eq!(inserter.hand(), None);
+    }
+
+    #[test]
+    fn blocked_01() {
+        let mut buffer: Buffer<char> = Buffer::new(2);
+        let mut belt: Belt<char> = "...c.".parse().unwrap();
+        let mut inserter = Inserter::new(3, 1);
+
+        assert_eq!(buffer.put_item('a'), Ok(()));
+
+        assert_eq!(
+            inserter.tick(&mut buffer, &mut BeltSlot::new(&mut belt, 3)),
+            State::Delivering(1)
+        );
+        assert_eq!(
+            inserter.tick(&mut buffer, &mut BeltSlot::new(&mut belt, 3)),
+            State::Blocked
+        );
+        assert_eq!(
+            inserter.tick(&mut buffer, &mut BeltSlot::new(&mut belt, 3)),
+            State::Blocked
+        );
+        assert_eq!(inserter.hand(), Some(&'a'));
+
+        assert_eq!(belt.remove_at(3), Ok('c'));
+
+        assert_eq!(
+            inserter.tick(&mut buffer, &mut BeltSlot::new(&mut belt, 3)),
+            State::Returning(1)
+        );
+        assert_eq!(belt.to_string(), "...a.");
+    }
+
+    #[test]
+    fn filter_01() {
+        let mut belt: Belt<char> = "..ab".parse().unwrap();
+        let mut buffer: Buffer<char> = Buffer::new(4);
+        let mut inserter = Inserter::new(0, 1);
+        inserter.set_filter(Some('a'));
+
+        assert_eq!(
+            inserter.tick(&mut BeltSlot::new(&mut belt, 3), &mut buffer),
+            State::Waiting
+        );
+        assert_eq!(
+            inserter.tick(&mut BeltSlot::new(&mut belt, 2), &mut buffer),
+            State::Delivering(1)
+        );
+        assert_eq!(inserter.hand(), Some(&'a'));
+        assert_eq!(belt.to_string(), "...b");
+    }
+}
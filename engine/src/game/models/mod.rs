@@ -1,3 +1,9 @@
 mod belt;
+mod buffer;
+mod inserter;
+mod stats;
 
-pub use belt::{Belt, Error};
+pub use belt::Belt;
+pub use buffer::Buffer;
+pub use inserter::{BeltSlot, Inserter, Sink, Source};
+pub use stats::Stats;
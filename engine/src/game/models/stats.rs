@@ -0,0 +1,233 @@
+use std::cmp;
+use std::collections::VecDeque;
+
+use crate::engine::TICKS_PER_SECOND;
+
+/// Number of simulation ticks in one minute of game time
+pub const TICKS_PER_MINUTE: usize = 60 * TICKS_PER_SECOND;
+
+/// Counters gathered over one or more ticks
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Sample {
+    /// Number of items that entered the belt
+    pub entered: usize,
+
+    /// Number of items that exited the belt
+    pub exited: usize,
+
+    /// Number of ticks the front of the belt held an item waiting to be taken
+    pub blocked: usize,
+
+    /// Number of ticks the back of the belt had space waiting to be filled
+    pub starved: usize,
+
+    /// Number of occupied slots summed over each tick
+    pub occupied: usize,
+}
+
+impl Sample {
+    fn add(&mut self, other: &Sample) {
+        self.entered += other.entered;
+        self.exited += other.exited;
+        self.blocked += other.blocked;
+        self.starved += other.starved;
+        self.occupied += other.occupied;
+    }
+
+    fn sub(&mut self, other: &Sample) {
+        self.entered -= other.entered;
+        self.exited -= other.exited;
+        self.blocked -= other.blocked;
+        self.starved -= other.starved;
+        self.occupied -= other.occupied;
+    }
+}
+
+/// Throughput and occupancy statistics over a sliding window of ticks. Ticks
+/// are summed into buckets so that long windows stay small.
+#[derive(Debug)]
+pub struct Stats {
+    /// Number of buckets kept in the window
+    buckets: usize,
+
+    /// Number of ticks summed into each bucket
+    bucket_ticks: usize,
+
+    /// Samples of the most recent full buckets, oldest first
+    samples: VecDeque<Sample>,
+
+    /// Sample of the bucket in progress
+    current: Sample,
+
+    /// Number of ticks summed into the bucket in progress
+    current_ticks: usize,
+
+    /// Sample of the tick in progress
+    tick: Sample,
+
+    /// Sum of all full buckets in the window
+    totals: Sample,
+}
+
+impl Stats {
+    /// Creates statistics kept over the last `buckets` buckets of
+    /// `bucket_ticks` ticks each
+    pub fn new(buckets: usize, bucket_ticks: usize) -> Stats {
+        Stats {
+            buckets,
+            bucket_ticks: cmp::max(bucket_ticks, 1),
+            samples: VecDeque::new(),
+            current: Sample::default(),
+            current_ticks: 0,
+            tick: Sample::default(),
+            totals: Sample::default(),
+        }
+    }
+
+    /// Records an item entering the belt during the current tick
+    pub fn record_entered(&mut self) {
+        self.tick.entered += 1;
+    }
+
+    /// Records an item exiting the belt during the current tick
+    pub fn record_exited(&mut self) {
+        self.tick.exited += 1;
+    }
+
+    /// Closes the current tick, sliding the window forward once a bucket is
+    /// full
+    pub fn end_tick(&mut self, blocked: bool, starved: bool, occupied: usize) {
+        let mut sample = std::mem::take(&mut self.tick);
+        sample.blocked = blocked as usize;
+        sample.starved = starved as usize;
+        sample.occupied = occupied;
+
+        self.current.add(&sample);
+        self.current_ticks += 1;
+        if self.current_ticks < self.bucket_ticks {
+            return;
+        }
+
+        let bucket = std::mem::take(&mut self.current);
+        self.current_ticks = 0;
+        self.totals.add(&bucket);
+        self.samples.push_back(bucket);
+
+        while self.samples.len() > self.buckets {
+            let oldest = self.samples.pop_front().unwrap();
+            self.totals.sub(&oldest);
+        }
+    }
+
+    /// Returns the number of ticks currently in the window
+    pub fn ticks(&self) -> usize {
+        self.samples.len() * self.bucket_ticks + self.current_ticks
+    }
+
+    /// Returns the counters summed over the window
+    pub fn totals(&self) -> Sample {
+        let mut totals = self.totals;
+        totals.add(&self.current);
+        totals
+    }
+
+    /// Returns the rate items exit the belt, in items per minute
+    pub fn items_per_minute(&self) -> f32 {
+        if self.ticks() == 0 {
+            return 0.0;
+        }
+
+        self.totals().exited as f32 * TICKS_PER_MINUTE as f32 / self.ticks() as f32
+    }
+
+    /// Returns the average percentage of `capacity` occupied over the window
+    pub fn saturation(&self, capacity: usize) -> f32 {
+        if self.ticks() == 0 || capacity == 0 {
+            return 0.0;
+        }
+
+        100.0 * self.totals().occupied as f32 / (self.ticks() * capacity) as f32
+    }
+
+    /// Returns the percentage of ticks in the window the front was blocked
+    pub fn blocked_percent(&self) -> f32 {
+        self.percent_of_ticks(self.totals().blocked)
+    }
+
+    /// Returns the percentage of ticks in the window the back was starved
+    pub fn starved_percent(&self) -> f32 {
+        self.percent_of_ticks(self.totals().starved)
+    }
+
+    fn percent_of_ticks(&self, count: usize) -> f32 {
+        if self.ticks() == 0 {
+            return 0.0;
+        }
+
+        100.0 * count as f32 / self.ticks() as f32
+    }
+}
+
+impl Default for Stats {
+    /// Keeps the last minute in one bucket per second
+    fn default() -> Stats {
+        Stats::new(60, TICKS_PER_SECOND)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn window_01() {
+        let mut stats = Stats::new(4, 1);
+
+        for tick in 0..6 {
+            stats.record_entered();
+            if tick % 2 == 0 {
+                stats.record_exited();
+            }
+            stats.end_tick(tick < 2, false, 2);
+        }
+
+        assert_eq!(stats.ticks(), 4);
+        assert_eq!(stats.totals().entered, 4);
+        assert_eq!(stats.totals().exited, 2);
+        assert_eq!(stats.totals().blocked, 0);
+        assert_eq!(stats.saturation(4), 50.0);
+        assert_eq!(stats.items_per_minute(), TICKS_PER_MINUTE as f32 / 2.0);
+    }
+
+    #[test]
+    fn empty_01() {
+        let stats = Stats::new(4, 1);
+
+        assert_eq!(stats.ticks(), 0);
+        assert_eq!(stats.items_per_minute(), 0.0);
+        assert_eq!(stats.saturation(4), 0.0);
+        assert_eq!(stats.blocked_percent(), 0.0);
+    }
+
+    #[test]
+    fn bucket_01() {
+        let mut stats = Stats::new(2, 3);
+
+        for tick in 0..8 {
+            stats.record_exited();
+            stats.end_tick(tick == 0, false, 1);
+        }
+
+        // The first bucket slid out of the window, the third is in progress.
+        assert_eq!(stats.ticks(), 8);
+        assert_eq!(stats.samples.len(), 2);
+        assert_eq!(stats.totals().exited, 8);
+
+        stats.record_exited();
+        stats.end_tick(false, false, 1);
+        assert_eq!(stats.ticks(), 6);
+        assert_eq!(stats.totals().exited, 6);
+        assert_eq!(stats.totals().blocked, 0);
+        assert_eq!(stats.saturation(1), 100.0);
+    }
+}
@@ -1,9 +1,11 @@
-use std::collections::HashMap;
+use crate::engine::{rng, Cell, CellCoord};
 
-use crate::engine::{CellCoord, rng};
+use super::super::models;
 
+/// Largest distance between the entry and exit of an underground belt.
+pub const UNDERGROUND_MAX_SPAN: i32 = 5;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Material {
     Mat1,
     Mat2,
@@ -12,24 +14,106 @@ pub enum Material {
     Mat5,
 }
 
+/// A section of belt that passes beneath the cells between its entry and exit.
 #[derive(Debug, Clone)]
+pub struct Underground {
+    pub entry: CellCoord,
+    pub exit: CellCoord,
+}
+
+impl Underground {
+    pub fn new(entry: CellCoord, exit: CellCoord) -> Underground {
+        Underground { entry, exit }
+    }
+
+    /// Number of belt slots between the entry and exit, including the exit.
+    pub fn span(&self) -> usize {
+        let x = (self.entry.x - self.exit.x).abs();
+        let y = (self.entry.y - self.exit.y).abs();
+        let z = (self.entry.z - self.exit.z).abs();
+
+        ((x + y + z) / 2) as usize
+    }
+}
+
+#[derive(Debug)]
 pub struct Belt {
     pub id: u32,
-    pub contents: HashMap<CellCoord, Option<Material>>,
+    /// Cells the belt is placed on, in the order items move along them.
+    pub path: Vec<CellCoord>,
+    pub undergrounds: Vec<Underground>,
+    /// Items moving along the belt. Every cell passed beneath by an
+    /// underground section takes a slot as well as the cells of the path.
+    pub items: models::Belt<Material>,
 }
 
 impl Belt {
-    pub fn new(contents: HashMap<CellCoord, Option<Material>>) -> Belt {
+    /// Creates an empty belt along a path from `shortest_path`. Consecutive
+    /// cells that are not neighbors become an underground section.
+    pub fn from_path<C>(path: &[C]) -> Belt
+    where
+        C: Cell,
+    {
+        let mut undergrounds = Vec::new();
+        for pair in path.windows(2) {
+            if pair[0].distance(&pair[1]) > 1 {
+                undergrounds.push(Underground::new(pair[0].coord(), pair[1].coord()));
+            }
+        }
+
+        let hidden: usize = undergrounds
+            .iter()
+            .map(|underground| underground.span() - 1)
+            .sum();
+
         Belt {
             id: rng::uid(),
-            contents: contents,
+            path: path.iter().map(|cell| cell.coord()).collect(),
+            undergrounds,
+            items: models::Belt::new(path.len() + hidden),
         }
     }
 
-    pub fn material_at(&self, coord: &CellCoord) -> &Option<Material> {
-        match self.contents.get(coord) {
-            Some(v) => v,
-            None => &None,
-        }
+    /// Returns the slot of `items` at `coord`, if the belt is placed on it.
+    /// Items are put at the first cell of the path and taken from the last.
+    pub fn slot_at(&self, coord: &CellCoord) -> Option<usize> {
+        let index = self.path.iter().position(|cell| cell == coord)?;
+        let hidden: usize = self
+            .undergrounds
+            .iter()
+            .filter(|underground| self.path[..index].contains(&underground.entry))
+            .map(|underground| underground.span() - 1)
+            .sum();
+
+        Some(index + hidden)
+    }
+
+    pub fn is_underground(&self, coord: &CellCoord) -> bool {
+        self.undergrounds
+            .iter()
+            .any(|underground| &underground.entry == coord || &underground.exit == coord)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::Hex;
+
+    fn hex(x: i32) -> Hex {
+        Hex::new(x as f32, 0.0, -x as f32)
+    }
+
+    #[test]
+    fn slot_at_01() {
+        // The cells passed beneath by an underground section take slots too.
+        let belt = Belt::from_path(&[hex(0), hex(1), hex(4), hex(5)]);
+
+        assert_eq!(belt.items.capacity(), 6);
+        assert!(belt.is_underground(&hex(1).coord()));
+        assert_eq!(belt.slot_at(&hex(1).coord()), Some(1));
+        assert_eq!(belt.slot_at(&hex(4).coord()), Some(4));
+        assert_eq!(belt.slot_at(&hex(5).coord()), Some(5));
+        assert_eq!(belt.slot_at(&hex(2).coord()), None);
     }
 }
@@ -2,6 +2,12 @@ use std::collections::HashMap;
 
 use crate::engine::{CellCoord, rng};
 
+use super::super::models::Buffer;
+use super::belt::Material;
+
+/// Number of materials a building can hold in its internal storage.
+pub const BUILDING_BUFFER_CAPACITY: usize = 10;
+
 #[derive(Debug, Clone)]
 pub enum BuildingState {
     // One or more belt nodes are not connected to a belt.
@@ -29,6 +35,7 @@ pub struct Building {
     pub id: u32,
     pub coord: CellCoord,
     pub state: BuildingState,
+    pub buffer: Buffer<Material>,
     nodes: HashMap<CellCoord, bool>,
 }
 
@@ -42,6 +49,7 @@ impl Building {
             id: rng::uid(),
             coord: coord,
             state: state,
+            buffer: Buffer::new(BUILDING_BUFFER_CAPACITY),
             nodes: nodes,
         }
     }
@@ -4,8 +4,8 @@ mod map_hex;
 mod renderer;
 mod world;
 
-pub use belt::{Belt, Material};
+pub use belt::{Belt, Material, UNDERGROUND_MAX_SPAN};
 pub use building::{Building, BuildingState};
 pub use map_hex::hex_map;
 pub use renderer::RenderError;
-pub use world::{GameState, UserAction, GAME_STATE, WORLD};
+pub use world::{GameState, InputAction, UserAction, EVENTS, GAME_STATE, INPUT, WORLD};
@@ -11,15 +11,18 @@ use serde::{Deserialize, Serialize};
 
 use crate::engine;
 use crate::engine::{
-    debug, Cell, CellCoord, Hex, HexLayout, HexOrientation, Layout, Point, Rectangle, World,
+    debug, Cell, CellCoord, EventDispatcher, Hex, HexLayout, HexOrientation, Input, InputBindings, Layout, Point, Rectangle, World,
 };
 
-use super::belt::Belt;
+use super::super::models::{BeltSlot, Buffer, Inserter, Sink, Source};
+use super::belt::{Belt, Material};
 use super::building::{Building, BuildingState};
 use super::map_hex::hex_map;
 
 use web_sys::{Document, Element, Event, MouseEvent, SvgElement, SvgsvgElement};
 
+const INPUT_BINDINGS: &str = include_str!("../../../../game/input/bindings.ron");
+
 // We cannot have mutable statics by default so we use this to enable it.
 thread_local! {
     pub static WORLD: RefCell<World<Hex, HexLayout>> = RefCell::new(
@@ -27,6 +30,14 @@ thread_local! {
     );
 
     pub static GAME_STATE: RefCell<GameState> = RefCell::new(GameState::new(load_world_map("map_hex")));
+
+    // Delivers mouse events to the sprites of the world.
+    pub static EVENTS: RefCell<EventDispatcher> = RefCell::new(EventDispatcher::new());
+
+    // Maps keys and mouse buttons to actions. Bindings can be changed while playing.
+    pub static INPUT: RefCell<InputBindings<InputAction>> = RefCell::new(
+        InputBindings::from_ron(INPUT_BINDINGS).expect("failed to load input bindings")
+    );
 }
 
 fn load_world_map(map_name: &str) -> HashSet<CellCoord> {
@@ -55,6 +66,32 @@ fn create_hex_world() -> World<Hex, HexLayout> {
     hex_world
 }
 
+/// Something the player can do with a key or mouse button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputAction {
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    ZoomIn,
+    ZoomOut,
+    /// Pans the camera while the input is held and the mouse is moved.
+    DragCamera,
+    ToggleLabels,
+    /// Starts placing a belt from the cell under the mouse.
+    Place,
+    /// Chooses a building to place, or puts it back if it was already chosen.
+    SelectBuilding,
+    /// Stops placing a belt or building without placing it.
+    CancelPlacement,
+    /// Turns the building under the mouse to face the next side of its cell.
+    Rotate,
+    /// Removes the building or belt under the mouse.
+    Delete,
+    /// Writes what the camera sees to the console as an SVG document, for bug reports.
+    ExportSvg,
+}
+
 #[derive(Debug)]
 pub enum UserAction {
     PlacingBelt {
@@ -68,8 +105,17 @@ pub enum UserAction {
 pub struct GameState {
     pub world: HashSet<CellCoord>,
     pub buildings: HashMap<CellCoord, Building>,
-    pub belts: HashMap<CellCoord, Vec<Belt>>,
+    pub belts: HashMap<u32, Belt>,
+    // Belts placed on each cell, by id.
+    belt_cells: HashMap<CellCoord, Vec<u32>>,
+    pub inserters: HashMap<CellCoord, Inserter<Material>>,
     pub current_action: Option<UserAction>,
+    /// Texture of the building chosen to be placed, if any.
+    pub selected_building: Option<String>,
+    /// Cell of the map under the mouse, if any.
+    pub hovered_cell: Option<CellCoord>,
+    /// Keys held down, so the camera pans for as long as they are.
+    pub held_keys: HashSet<Input>,
 }
 
 impl GameState {
@@ -78,7 +124,12 @@ impl GameState {
             world: world,
             buildings: HashMap::new(),
             belts: HashMap::new(),
+            belt_cells: HashMap::new(),
+            inserters: HashMap::new(),
             current_action: None,
+            selected_building: None,
+            hovered_cell: None,
+            held_keys: HashSet::new(),
         }
     }
 
@@ -91,16 +142,77 @@ impl GameState {
     }
 
     pub fn add_belt(&mut self, belt: Belt) {
-        for (coord, _) in &belt.contents {
-            let cell_belts = self.belts.entry(coord.clone()).or_insert(Vec::new());
-            cell_belts.push(belt.clone());
+        for coord in &belt.path {
+            let cell_belts = self.belt_cells.entry(coord.clone()).or_insert(Vec::new());
+            cell_belts.push(belt.id);
+        }
+        self.belts.insert(belt.id, belt);
+    }
+
+    pub fn remove_belt(&mut self, belt_id: u32) -> Option<Belt> {
+        let belt = self.belts.remove(&belt_id)?;
+        for coord in &belt.path {
+            if let Some(cell_belts) = self.belt_cells.get_mut(coord) {
+                cell_belts.retain(|id| *id != belt_id);
+            }
+        }
+        // Cells without belts no longer collide.
+        self.belt_cells.retain(|_, cell_belts| !cell_belts.is_empty());
+
+        Some(belt)
+    }
+
+    pub fn add_inserter(&mut self, coord: CellCoord, inserter: Inserter<Material>) {
+        self.inserters.insert(coord, inserter);
+    }
+
+    pub fn remove_inserter(&mut self, coord: &CellCoord) {
+        self.inserters.remove(coord);
+    }
+
+    /// Moves every belt and inserter forward one tick. Inserters pick from the
+    /// belt or building in their direction and drop into the one opposite it.
+    pub fn tick(&mut self) {
+        for belt in self.belts.values_mut() {
+            belt.items.tick();
+        }
+
+        // Find what each inserter reaches before borrowing it mutably.
+        let reaches: Vec<(CellCoord, Reach, Reach)> = self
+            .inserters
+            .iter()
+            .map(|(coord, inserter)| {
+                let cell = Hex::new(coord.x as f32, coord.y as f32, coord.z as f32);
+                let pick = cell.neighbor(inserter.direction());
+                let drop = cell.neighbor(cell.opposite_direction(inserter.direction()));
+                (*coord, self.reach(&pick.coord()), self.reach(&drop.coord()))
+            })
+            .collect();
+
+        for (coord, pick, drop) in reaches {
+            let inserter = self
+                .inserters
+                .get_mut(&coord)
+                .expect("inserter should exist");
+            let (mut source, mut sink) =
+                Side::both(&mut self.belts, &mut self.buildings, pick, drop);
+            inserter.tick(&mut source, &mut sink);
         }
     }
 
-    pub fn remove_belt(&mut self, belt_id: u32) {
-        for (_, cell_belts) in &mut self.belts {
-            cell_belts.retain(|belt| belt.id != belt_id);
+    // Returns what an inserter reaches into at `coord`.
+    fn reach(&self, coord: &CellCoord) -> Reach {
+        if self.buildings.contains_key(coord) {
+            return Reach::Building(*coord);
         }
+
+        for belt in self.belts_at(coord) {
+            if let Some(slot) = belt.slot_at(coord) {
+                return Reach::Belt(belt.id, slot);
+            }
+        }
+
+        Reach::Nothing
     }
 
     pub fn collisions(&self) -> HashSet<CellCoord> {
@@ -108,18 +220,26 @@ impl GameState {
         for (coord, _) in &self.buildings {
             collision_set.insert(coord.clone());
         }
-        for (coord, _) in &self.belts {
-            collision_set.insert(coord.clone());
+        for coord in self.belt_cells.keys() {
+            collision_set.insert(*coord);
+        }
+        for coord in self.inserters.keys() {
+            collision_set.insert(*coord);
         }
 
         collision_set
     }
 
-    pub fn belts_at(&self, coord: &CellCoord) -> Option<&[Belt]> {
-        if let Some(v) = self.belts.get(coord) {
-            return Some(v.as_slice());
+    pub fn belts_at(&self, coord: &CellCoord) -> Vec<&Belt> {
+        match self.belt_cells.get(coord) {
+            Some(ids) => ids.iter().filter_map(|id| self.belts.get(id)).collect(),
+            None => Vec::new(),
         }
-        return None;
+    }
+
+    /// Indicates if something can be placed at `coord`, which must be on the map and free.
+    pub fn can_place_at(&self, coord: &CellCoord) -> bool {
+        self.world.contains(coord) && !self.collisions().contains(coord)
     }
 
     pub fn building_at(&self, coord: &CellCoord) -> Option<&Building> {
@@ -158,6 +278,113 @@ impl GameState {
     */
 }
 
+// Belt slot or building an inserter reaches into, found by id or coord.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Reach {
+    Belt(u32, usize),
+    Building(CellCoord),
+    Nothing,
+}
+
+// Belt slot or building buffer an inserter picks from or drops into.
+enum Side<'a> {
+    Belt(BeltSlot<'a, Material>),
+    Building(&'a mut Buffer<Material>),
+    Nothing,
+}
+
+impl<'a> Side<'a> {
+    // Borrows both sides of an inserter at once. An inserter cannot pick from
+    // and drop into the same belt or building, so the drop side is then left out.
+    fn both(
+        belts: &'a mut HashMap<u32, Belt>,
+        buildings: &'a mut HashMap<CellCoord, Building>,
+        pick: Reach,
+        drop: Reach,
+    ) -> (Side<'a>, Side<'a>) {
+        match (pick, drop) {
+            (Reach::Belt(pick_id, pick_slot), Reach::Belt(drop_id, drop_slot))
+                if pick_id != drop_id =>
+            {
+                match belts.get_disjoint_mut([&pick_id, &drop_id]) {
+                    [Some(pick_belt), Some(drop_belt)] => (
+                        Side::Belt(BeltSlot::new(&mut pick_belt.items, pick_slot)),
+                        Side::Belt(BeltSlot::new(&mut drop_belt.items, drop_slot)),
+                    ),
+                    _ => (Side::Nothing, Side::Nothing),
+                }
+            }
+            (Reach::Building(pick_coord), Reach::Building(drop_coord))
+                if pick_coord != drop_coord =>
+            {
+                match buildings.get_disjoint_mut([&pick_coord, &drop_coord]) {
+                    [Some(pick_building), Some(drop_building)] => (
+                        Side::Building(&mut pick_building.buffer),
+                        Side::Building(&mut drop_building.buffer),
+                    ),
+                    _ => (Side::Nothing, Side::Nothing),
+                }
+            }
+            (Reach::Belt(id, slot), Reach::Building(coord)) => (
+                Side::belt(belts, id, slot),
+                Side::building(buildings, coord),
+            ),
+            (Reach::Building(coord), Reach::Belt(id, slot)) => (
+                Side::building(buildings, coord),
+                Side::belt(belts, id, slot),
+            ),
+            (Reach::Nothing, _) => (Side::Nothing, Side::one(belts, buildings, drop)),
+            _ => (Side::one(belts, buildings, pick), Side::Nothing),
+        }
+    }
+
+    fn one(
+        belts: &'a mut HashMap<u32, Belt>,
+        buildings: &'a mut HashMap<CellCoord, Building>,
+        reach: Reach,
+    ) -> Side<'a> {
+        match reach {
+            Reach::Belt(id, slot) => Side::belt(belts, id, slot),
+            Reach::Building(coord) => Side::building(buildings, coord),
+            Reach::Nothing => Side::Nothing,
+        }
+    }
+
+    fn belt(belts: &'a mut HashMap<u32, Belt>, id: u32, slot: usize) -> Side<'a> {
+        match belts.get_mut(&id) {
+            Some(belt) => Side::Belt(BeltSlot::new(&mut belt.items, slot)),
+            None => Side::Nothing,
+        }
+    }
+
+    fn building(buildings: &'a mut HashMap<CellCoord, Building>, coord: CellCoord) -> Side<'a> {
+        match buildings.get_mut(&coord) {
+            Some(building) => Side::Building(&mut building.buffer),
+            None => Side::Nothing,
+        }
+    }
+}
+
+impl<'a> Source<Material> for Side<'a> {
+    fn take_matching(&mut self, filter: Option<&Material>) -> Option<Material> {
+        match self {
+            Side::Belt(slot) => slot.take_matching(filter),
+            Side::Building(buffer) => buffer.take_matching(filter),
+            Side::Nothing => None,
+        }
+    }
+}
+
+impl<'a> Sink<Material> for Side<'a> {
+    fn put_item(&mut self, item: Material) -> Result<(), Material> {
+        match self {
+            Side::Belt(slot) => slot.put_item(item),
+            Side::Building(buffer) => buffer.put_item(item),
+            Side::Nothing => Err(item),
+        }
+    }
+}
+
 // pub fn generate_hexgon(&mut self, radius: i32) {
 //     self.cells.clear();
 
@@ -171,3 +398,38 @@ impl GameState {
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hex(x: i32) -> Hex {
+        Hex::new(x as f32, 0.0, -x as f32)
+    }
+
+    #[test]
+    fn tick_01() {
+        let mut game_state = GameState::new(HashSet::new());
+
+        // A belt moves items towards an inserter, which drops them into the building opposite.
+        let mut belt = Belt::from_path(&[hex(0), hex(1)]);
+        belt.items.put(Material::Mat1).unwrap();
+        let belt_id = belt.id;
+        game_state.add_belt(belt);
+        game_state.add_inserter(hex(2).coord(), Inserter::new(3, 2));
+        game_state.add_building(Building::new(
+            hex(3).coord(),
+            HashMap::new(),
+            BuildingState::Working,
+        ));
+
+        for _ in 0..100 {
+            game_state.tick();
+        }
+
+        assert_eq!(game_state.belts[&belt_id].items.count(), 0);
+        let building = &game_state.buildings[&hex(3).coord()];
+        assert_eq!(building.buffer.count_of(&Material::Mat1), 1);
+        assert_eq!(game_state.inserters[&hex(2).coord()].hand(), None);
+    }
+}